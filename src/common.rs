@@ -45,6 +45,10 @@ impl<K: Ord + Copy> OrderedCounter<K> {
     pub fn is_empty(&self) -> bool {
         return self.bst.is_empty();
     }
+
+    pub fn iter(&self) -> impl Iterator<Item=(K, usize)> + '_ {
+        return self.bst.iter().map(|(&k, &v)| (k, v));
+    }
 }
 
 #[derive(Debug, Clone)]
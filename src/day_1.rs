@@ -1,35 +1,241 @@
 use std::{fs, io};
-use std::collections::{HashMap, HashSet};
-use std::io::BufRead;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, Seek, SeekFrom, Write};
+use std::iter::repeat;
+use itertools::{EitherOrBoth, Itertools};
 use crate::common::OrderedCounter;
 
+// The puzzle lists are of even length, uneven ones are compared up to the length of the shorter list.
+const PUZZLE_POLICY: UnevenPolicy = UnevenPolicy::Truncate;
+
 pub fn part_1(data: fs::File) -> usize {
-    let mut left = OrderedCounter::<usize>::new();
-    let mut right = OrderedCounter::<usize>::new();
+    let (left, right) = read_counters(data, PUZZLE_POLICY).expect("Truncating never fails");
 
-    for line in io::BufReader::new(data).lines().flatten() {
-        let mut numbers = line.split_ascii_whitespace().map(|x| x.parse::<usize>().unwrap());
-        left.increment(numbers.next().unwrap());
-        right.increment(numbers.next().unwrap());
+    return expand(&left).zip(expand(&right)).map(|(l, r)| l.abs_diff(r)).sum();
+}
+
+pub fn part_2(data: fs::File) -> usize {
+    let (left, right) = read_counters(data, PUZZLE_POLICY).expect("Truncating never fails");
+
+    return left.iter()
+        .merge_join_by(right.iter(), |(a, _), (b, _)| a.cmp(b))
+        .map(|entry| match entry {
+            EitherOrBoth::Both((id, left_count), (_, right_count)) => id * left_count * right_count,
+            _ => 0,
+        })
+        .sum();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconciliationEntry {
+    Pair { left: usize, right: usize, distance: usize },
+    Similarity { id: usize, left_count: usize, right_count: usize, contribution: usize },
+    LeftOnly { id: usize, count: usize },
+    RightOnly { id: usize, count: usize },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReconciliationSummary {
+    pub total_distance: usize,
+    pub similarity: usize,
+    // the largest distances of the sorted pairing as (left, right, distance), largest first
+    pub top_discrepancies: Vec<(usize, usize, usize)>,
+}
+
+fn expand(counter: &OrderedCounter<usize>) -> impl Iterator<Item=usize> + '_ {
+    return counter.iter().flat_map(|(id, count)| repeat(id).take(count));
+}
+
+// Reads the first two lists, applying the policy if their lengths differ.
+fn read_counters(data: fs::File, policy: UnevenPolicy) -> Result<(OrderedCounter<usize>, OrderedCounter<usize>), UnevenListsError> {
+    let (_, mut counters) = read_columns(data, Some(2), policy)?;
+    let right = counters.swap_remove(1);
+    let left = counters.swap_remove(0);
+
+    return Ok((left, right));
+}
+
+// Streams the reconciliation of both lists to emit, first the sorted pairing and then the per id
+// entries in ascending id order. Only the counters of both lists are held in memory, such that
+// repeated ids are stored once.
+pub fn reconcile(
+    data: fs::File,
+    top_k: usize,
+    policy: UnevenPolicy,
+    mut emit: impl FnMut(ReconciliationEntry),
+) -> Result<ReconciliationSummary, UnevenListsError> {
+    let (left, right) = read_counters(data, policy)?;
+
+    let mut summary = ReconciliationSummary::default();
+    let mut top = BinaryHeap::new();
+
+    for (l, r) in expand(&left).zip(expand(&right)) {
+        let distance = l.abs_diff(r);
+        summary.total_distance += distance;
+
+        top.push(Reverse((distance, l, r)));
+        if top.len() > top_k {
+            top.pop();
+        }
+
+        emit(ReconciliationEntry::Pair { left: l, right: r, distance });
     }
 
-    let mut total = 0;
-    while let Some(left_val) = left.pop_first() {
-        total += left_val.abs_diff(right.pop_first().unwrap());
+    for entry in left.iter().merge_join_by(right.iter(), |(a, _), (b, _)| a.cmp(b)) {
+        emit(match entry {
+            EitherOrBoth::Both((id, left_count), (_, right_count)) => {
+                let contribution = id * left_count * right_count;
+                summary.similarity += contribution;
+                ReconciliationEntry::Similarity { id, left_count, right_count, contribution }
+            },
+            EitherOrBoth::Left((id, count)) => ReconciliationEntry::LeftOnly { id, count },
+            EitherOrBoth::Right((id, count)) => ReconciliationEntry::RightOnly { id, count },
+        });
     }
 
-    return total;
+    summary.top_discrepancies = top.into_sorted_vec().into_iter().map(|Reverse(x)| (x.1, x.2, x.0)).collect();
+
+    return Ok(summary);
 }
 
-pub fn part_2(data: fs::File) -> usize {
-    let mut left = HashSet::<usize>::new();
-    let mut right = HashMap::<usize, usize>::new();
+pub fn write_report(data: fs::File, top_k: usize, policy: UnevenPolicy, out: &mut impl Write) -> io::Result<()> {
+    let mut result = Ok(());
+
+    let summary = reconcile(data, top_k, policy, |entry| {
+        if result.is_err() { return; }
+
+        result = match entry {
+            ReconciliationEntry::Pair { left, right, distance } =>
+                writeln!(out, "pair {left} {right} distance {distance}"),
+            ReconciliationEntry::Similarity { id, left_count, right_count, contribution } =>
+                writeln!(out, "similarity {id} left x{left_count} right x{right_count} contribution {contribution}"),
+            ReconciliationEntry::LeftOnly { id, count } =>
+                writeln!(out, "left-only {id} x{count}"),
+            ReconciliationEntry::RightOnly { id, count } =>
+                writeln!(out, "right-only {id} x{count}"),
+        };
+    }).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    result?;
+
+    writeln!(out, "total distance {}", summary.total_distance)?;
+    writeln!(out, "similarity {}", summary.similarity)?;
+
+    for (left, right, distance) in summary.top_discrepancies {
+        writeln!(out, "discrepancy {left} {right} distance {distance}")?;
+    }
 
+    return Ok(());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnevenPolicy {
+    Error,
+    // extends shorter lists with the given id
+    Pad(usize),
+    // drops the trailing ids of longer lists
+    Truncate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnevenListsError {
+    pub lengths: Vec<usize>,
+}
+
+impl Display for UnevenListsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "lists have uneven lengths {:?}", self.lengths);
+    }
+}
+
+impl Error for UnevenListsError {}
+
+#[derive(Debug, Clone)]
+pub struct ColumnComparison {
+    // the lengths of the lists as read, before applying the policy
+    pub lengths: Vec<usize>,
+    // distance[i][j] is the total distance of the sorted pairing of lists i and j
+    pub distance: Vec<Vec<usize>>,
+    // similarity[i][j] is the sum of every id of list i times its occurrences in list j
+    pub similarity: Vec<Vec<usize>>,
+}
+
+// Calls visit with the column and value of every number on every line, where the i-th number of
+// each line belongs to the i-th list, limited to the given number of lists if any.
+fn for_each_number(data: &fs::File, column_count: Option<usize>, mut visit: impl FnMut(usize, usize)) {
     for line in io::BufReader::new(data).lines().flatten() {
-        let mut numbers = line.split_ascii_whitespace().map(|x| x.parse::<usize>().unwrap());
-        left.insert(numbers.next().unwrap());
-        right.entry(numbers.next().unwrap()).and_modify(|v| { *v += 1 }).or_insert(1);
+        let numbers = line.split_ascii_whitespace().take(column_count.unwrap_or(usize::MAX));
+        numbers.map(|x| x.parse::<usize>().unwrap()).enumerate().for_each(|(i, x)| visit(i, x));
     }
+}
+
+// Reads the given number of lists, or every list if none is given, and returns their lengths as
+// read together with their counters after applying the policy to the lists read. The counters are
+// filled while streaming, such that the lists themselves are never held in memory.
+fn read_columns(
+    data: fs::File,
+    column_count: Option<usize>,
+    policy: UnevenPolicy,
+) -> Result<(Vec<usize>, Vec<OrderedCounter<usize>>), UnevenListsError> {
+    let mut counters: Vec<OrderedCounter<usize>> = (0..column_count.unwrap_or(0)).map(|_| OrderedCounter::new()).collect();
+    let mut lengths = vec![0; counters.len()];
 
-    return left.into_iter().map(|x| x * right.get(&x).cloned().unwrap_or(0)).sum();
-}
\ No newline at end of file
+    for_each_number(&data, column_count, |i, x| {
+        if i == counters.len() {
+            counters.push(OrderedCounter::new());
+            lengths.push(0);
+        }
+
+        counters[i].increment(x);
+        lengths[i] += 1;
+    });
+
+    let min_len = lengths.iter().cloned().min().unwrap_or(0);
+    let max_len = lengths.iter().cloned().max().unwrap_or(0);
+
+    if min_len != max_len {
+        match policy {
+            UnevenPolicy::Error => { return Err(UnevenListsError { lengths }); },
+            UnevenPolicy::Pad(id) => {
+                for (counter, &len) in counters.iter_mut().zip(lengths.iter()) {
+                    (len..max_len).for_each(|_| { counter.increment(id); });
+                }
+            },
+            UnevenPolicy::Truncate => {
+                // the trailing ids are only known by their position, hence they are removed again
+                // in a second pass over the file
+                (&data).seek(SeekFrom::Start(0)).unwrap();
+                let mut seen = vec![0; counters.len()];
+
+                for_each_number(&data, column_count, |i, x| {
+                    if seen[i] >= min_len {
+                        counters[i].decrement(&x);
+                    }
+                    seen[i] += 1;
+                });
+            },
+        }
+    }
+
+    return Ok((lengths, counters));
+}
+
+// Compares every pair of columns, where the i-th number of each line belongs to the i-th list.
+pub fn compare_columns(data: fs::File, policy: UnevenPolicy) -> Result<ColumnComparison, UnevenListsError> {
+    let (lengths, counters) = read_columns(data, None, policy)?;
+
+    let n = counters.len();
+    let mut distance = vec![vec![0; n]; n];
+    let mut similarity = vec![vec![0; n]; n];
+
+    for i in 0..n {
+        for j in 0..n {
+            distance[i][j] = expand(&counters[i]).zip(expand(&counters[j])).map(|(a, b)| a.abs_diff(b)).sum();
+            similarity[i][j] = counters[i].iter().map(|(id, count)| id * count * counters[j].get(&id).unwrap_or(0)).sum();
+        }
+    }
+
+    return Ok(ColumnComparison { lengths, distance, similarity });
+}
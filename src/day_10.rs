@@ -1,80 +1,169 @@
-use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::RangeInclusive;
+use itertools::Itertools;
 use crate::common::Matrix;
 
-pub fn part_1(data: File) -> usize {
-    let matrix = Matrix::from_lines(
-        BufReader::new(data).lines().flatten(),
-        |c, _| c.to_digit(10).unwrap() as usize
-    );
+// Which trails are hiking trails. Heights are read as base 36 digits, such that heights above 9
+// can be written as letters, and every other character is impassable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrailRules {
+    // trails start at the lowest and end at the highest height, all cells in between are within
+    pub heights: RangeInclusive<u32>,
+    // the allowed climbs between adjacent cells, which have to be positive such that trails are finite
+    pub steps: RangeInclusive<u32>,
+}
+
+impl Default for TrailRules {
+    fn default() -> TrailRules {
+        return TrailRules { heights: 0..=9, steps: 1..=1 };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trailhead {
+    pub position: (isize, isize),
+    // the number of distinct trail ends reachable
+    pub score: usize,
+    // the number of distinct trails
+    pub rating: usize,
+}
+
+// The hiking trails of a topographic map. Every step climbs, hence the cells form a directed acyclic
+// graph, and processing them from the highest to the lowest counts the trails and reachable ends
+// of all cells at once rather than searching from every trailhead separately.
+pub struct Trails {
+    grid: Matrix<char>,
+    rules: TrailRules,
+    // the number of trails from every cell to an end
+    paths: Vec<usize>,
+    // the ends reachable from every cell as bitset over the ends, empty for cells without trails
+    ends: Vec<Vec<u64>>,
+}
+
+impl Trails {
+    pub fn new(grid: Matrix<char>, rules: TrailRules) -> Trails {
+        assert!(*rules.steps.start() > 0, "Trails have to climb with every step");
 
-    let mut total = 0;
+        let mut trails = Trails { paths: vec![0; grid.buffer.len()], ends: vec![Vec::new(); grid.buffer.len()], grid, rules };
 
-    for i in 0..matrix.buffer.len() {
-        if matrix.buffer[i] == 0 {
-            let mut front = HashSet::new();
-            front.insert(matrix.index_to_point(i).unwrap());
+        let end_count = (0..trails.grid.buffer.len()).filter(|&i| trails.height(i) == Some(*trails.rules.heights.end())).count();
+        let order = (0..trails.grid.buffer.len())
+            .filter_map(|i| trails.height(i).map(|h| (h, i)))
+            .sorted_unstable_by(|a, b| b.cmp(a))
+            .map(|(_, i)| i)
+            .collect_vec();
 
-            for j in 1..=9 {
-                let mut next = HashSet::new();
+        let mut next_end = 0;
 
-                for p in front.drain() {
-                    for d in [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter() {
-                        let np = (p.0 + d.0, p.1 + d.1);
+        for i in order {
+            let mut ends = vec![0u64; end_count.div_ceil(64)];
 
-                        if let Some(npi) = matrix.point_to_index(np) {
-                            if matrix.buffer[npi] == j {
-                                next.insert(np);
-                            }
-                        }
+            if trails.height(i) == Some(*trails.rules.heights.end()) {
+                trails.paths[i] = 1;
+                ends[next_end / 64] |= 1 << (next_end % 64);
+                next_end += 1;
+            } else {
+                for j in trails.successors(i).collect_vec() {
+                    trails.paths[i] += trails.paths[j];
+
+                    for (word, other) in ends.iter_mut().zip(trails.ends[j].iter()) {
+                        *word |= other;
                     }
                 }
-
-                front = next;
             }
 
-            total += front.len();
+            if trails.paths[i] > 0 {
+                trails.ends[i] = ends;
+            }
         }
-    }
 
-    return total;
-}
+        return trails;
+    }
 
-pub fn part_2(data: File) -> usize {
-    let matrix = Matrix::from_lines(
-        BufReader::new(data).lines().flatten(),
-        |c, _| c.to_digit(10).unwrap() as usize
-    );
+    pub fn read(data: File, rules: TrailRules) -> Trails {
+        return Trails::new(Matrix::from_lines(BufReader::new(data).lines().flatten(), |x, _| x), rules);
+    }
 
-    let mut total = 0;
+    fn height(&self, i: usize) -> Option<u32> {
+        return self.grid.buffer[i].to_digit(36).filter(|h| self.rules.heights.contains(h));
+    }
 
-    for i in 0..matrix.buffer.len() {
-        if matrix.buffer[i] == 0 {
-            let mut front = HashMap::new();
-            front.insert(matrix.index_to_point(i).unwrap(), 1);
+    fn successors(&self, i: usize) -> impl Iterator<Item=usize> + '_ {
+        let h = self.height(i);
+        let p = self.grid.index_to_point(i).unwrap();
 
-            for j in 1..=9 {
-                let mut next = HashMap::new();
+        return self.grid.neighborhood_four_way(p)
+            .map(|(q, _)| self.grid.point_to_index(q).unwrap())
+            .filter(move |&j| h.zip(self.height(j)).is_some_and(|(h, next)| next > h && self.rules.steps.contains(&(next - h))));
+    }
 
-                for (p, p_count) in front.drain() {
-                    for d in [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter() {
-                        let next_p = (p.0 + d.0, p.1 + d.1);
+    pub fn trailheads(&self) -> Vec<Trailhead> {
+        return (0..self.grid.buffer.len())
+            .filter(|&i| self.height(i) == Some(*self.rules.heights.start()))
+            .map(|i| Trailhead {
+                position: self.grid.index_to_point(i).unwrap(),
+                score: self.ends[i].iter().map(|word| word.count_ones() as usize).sum(),
+                rating: self.paths[i],
+            })
+            .collect();
+    }
 
-                        if let Some(npi) = matrix.point_to_index(next_p) {
-                            if matrix.buffer[npi] == j {
-                                next.entry(next_p).and_modify(|v| *v += p_count).or_insert(p_count);
-                            }
-                        }
-                    }
+    // Emits every trail starting at the cell, as the cells along it, visiting only cells that lead
+    // to an end.
+    pub fn enumerate(&self, start: (isize, isize), mut emit: impl FnMut(&[(isize, isize)])) {
+        fn search(trails: &Trails, i: usize, trail: &mut Vec<(isize, isize)>, emit: &mut impl FnMut(&[(isize, isize)])) {
+            trail.push(trails.grid.index_to_point(i).unwrap());
+
+            if trails.height(i) == Some(*trails.rules.heights.end()) {
+                emit(trail);
+            } else {
+                for j in trails.successors(i).filter(|&j| trails.paths[j] > 0) {
+                    search(trails, j, trail, emit);
                 }
-
-                front = next;
             }
 
-            total += front.drain().map(|(_, v)| v).sum::<usize>();
+            trail.pop();
+        }
+
+        if let Some(i) = self.grid.point_to_index(start).filter(|&i| self.paths[i] > 0) {
+            search(self, i, &mut Vec::new(), &mut emit);
         }
     }
+}
+
+pub fn write_report(data: File, rules: TrailRules, enumerate: bool, out: &mut impl Write) -> io::Result<()> {
+    let trails = Trails::read(data, rules);
+    let trailheads = trails.trailheads();
+
+    for trailhead in trailheads.iter() {
+        writeln!(
+            out, "{},{}: score {}, rating {}",
+            trailhead.position.0, trailhead.position.1, trailhead.score, trailhead.rating,
+        )?;
 
-    return total;
-}
\ No newline at end of file
+        if enumerate {
+            let mut result = Ok(());
+
+            trails.enumerate(trailhead.position, |trail| if result.is_ok() {
+                result = writeln!(out, "  {}", trail.iter().map(|(x, y)| format!("{x},{y}")).join(" "));
+            });
+            result?;
+        }
+    }
+
+    return writeln!(
+        out, "{} trailheads, total score {}, total rating {}",
+        trailheads.len(),
+        trailheads.iter().map(|trailhead| trailhead.score).sum::<usize>(),
+        trailheads.iter().map(|trailhead| trailhead.rating).sum::<usize>(),
+    );
+}
+
+pub fn part_1(data: File) -> usize {
+    return Trails::read(data, TrailRules::default()).trailheads().iter().map(|trailhead| trailhead.score).sum();
+}
+
+pub fn part_2(data: File) -> usize {
+    return Trails::read(data, TrailRules::default()).trailheads().iter().map(|trailhead| trailhead.rating).sum();
+}
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use log::{debug, trace};
 
 #[derive(Clone, Debug)]
 enum Tile {
@@ -50,27 +51,34 @@ pub fn part_1(data: File) -> usize {
         };
     }
 
-    macro_rules! print_tiles {
+    macro_rules! tiles_string {
         () => { {
+            let mut result = String::new();
+
             for (i, v) in field.iter().enumerate() {
                 if i != 0 && i % width == 0 {
-                    print!("\n");
+                    result.push('\n');
                 }
 
                 if p.0 == (i % width) as isize && p.1 == (i / width) as isize {
-                    print!("@");
+                    result.push('@');
                 } else {
-                    print!("{}", match v {
+                    result.push(match v {
                         Tile::Empty => '.',
                         Tile::Wall => '#',
                         Tile::Box => 'O'
                     });
                 }
             }
-            print!("\n");
+
+            result
         } };
     }
 
+    trace!(target: "day15", "Initial warehouse\n{}", tiles_string!());
+
+    let mut moves = 0;
+
     for line in lines {
         for v in line.chars() {
             let dir = match v {
@@ -110,11 +118,13 @@ pub fn part_1(data: File) -> usize {
                 }
             };
 
-            //print_tiles!();
-            //println!();
+            moves += 1;
+            trace!(target: "day15", "Move {moves} {v}\n{}", tiles_string!());
         }
     }
 
+    debug!(target: "day15", "Simulated {moves} moves");
+
     let mut total = 0;
 
     for (i, v) in field.iter().enumerate() {
@@ -187,17 +197,19 @@ pub fn part_2(data: File) -> usize {
         };
     }
 
-    macro_rules! print_tiles {
+    macro_rules! tiles_string {
         () => { {
+            let mut result = String::new();
+
             for (i, v) in field.iter().enumerate() {
                 if i != 0 && i % width == 0 {
-                    print!("\n");
+                    result.push('\n');
                 }
 
                 if p.0 == (i % width) as isize && p.1 == (i / width) as isize {
-                    print!("@");
+                    result.push('@');
                 } else {
-                    print!("{}", match v {
+                    result.push(match v {
                         TileWide::Empty => '.',
                         TileWide::Wall => '#',
                         TileWide::Box(false) => '[',
@@ -205,12 +217,14 @@ pub fn part_2(data: File) -> usize {
                     });
                 }
             }
-            print!("\n");
+
+            result
         } };
     }
     
-    //print_tiles!();
-    //println!("");
+    trace!(target: "day15", "Initial warehouse\n{}", tiles_string!());
+
+    let mut moves = 0;
 
     for line in lines {
         for v in line.chars() {
@@ -297,11 +311,13 @@ pub fn part_2(data: File) -> usize {
                 }
             };
 
-            //print_tiles!();
-            //println!();
+            moves += 1;
+            trace!(target: "day15", "Move {moves} {v}\n{}", tiles_string!());
         }
     }
 
+    debug!(target: "day15", "Simulated {moves} moves");
+
     let mut total = 0;
 
     for (i, v) in field.iter().enumerate() {
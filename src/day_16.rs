@@ -2,10 +2,12 @@ use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry::Vacant;
 use priority_queue::PriorityQueue;
+use log::debug;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::iter::once;
 use crate::common::Matrix;
+use crate::stats;
 
 #[derive(Clone, Debug, Copy, Hash, PartialEq, Eq)]
 enum Direction {
@@ -63,6 +65,8 @@ pub fn part_1(data: File) -> usize {
             let (pos, dir) = loc;
 
             if pos == end {
+                debug!(target: "day16", "Expanded {} nodes; Frontier {}", found.len(), queue.len());
+                stats::count("day16", "nodes_expanded", found.len());
                 return dist;
             }
 
@@ -135,6 +139,9 @@ fn dijkstra(
         }
     }
 
+    debug!(target: "day16", "Expanded {} nodes; Reversed {reversed}", found.len());
+    stats::count("day16", "nodes_expanded", found.len());
+
     return found;
 }
 
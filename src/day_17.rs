@@ -2,8 +2,10 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::mem::swap;
 use itertools::Itertools;
+use log::debug;
+use crate::stats;
 
-fn parse_program(data: File) -> (Vec<usize>, [usize; 3]) {
+pub fn parse_program(data: File) -> (Vec<usize>, [usize; 3]) {
     let mut lines = BufReader::new(data).lines().flatten();
 
     let registers = [
@@ -22,7 +24,7 @@ fn parse_program(data: File) -> (Vec<usize>, [usize; 3]) {
     return (code, registers);
 }
 
-fn eval_program(
+pub fn eval_program(
     code: &[usize],
     registers: &[usize; 3],
     mut pointer_callback: impl FnMut(usize),
@@ -87,6 +89,9 @@ pub fn part_1(data: File) -> String {
 pub fn part_2(data: File) -> usize {
     let (code, _) = parse_program(data);
 
+    // the constant the low bits of A are xored with to obtain the shift of the cdv instruction
+    let mask = code.chunks(2).find(|x| x[0] == 1).map_or(0, |x| x[1]);
+
     let mut k = 0;
     let mut nodes: Vec<(usize, usize)> = Vec::new();
     let mut next_nodes = Vec::new();
@@ -95,7 +100,7 @@ pub fn part_2(data: File) -> usize {
     
     // nodes represents possible initial bits for register A,
     // each iteration depends on bits A[3k...3(k+1)] 
-    // and for m=((A >> 3k) % 8)^1 on A[3k+m...3(k+1)+m]
+    // and for m=((A >> 3k) % 8)^mask on A[3k+m...3(k+1)+m]
     // hence each iteration we guess the bits that the iteration depends upon, check whether
     // the output up to that iteration is correct and add the guesses that are valid
     
@@ -108,18 +113,20 @@ pub fn part_2(data: File) -> usize {
             for bits_p1_comp in 0..(1 << rem_bits_p1) {
                 let bits_p1 = (bits_p1_comp << bit_count) | bits;
                 
-                // now, A[3k+m...3(k+1)+m] with m=((A >> 3k) % 8)^1 is also used by iteration k
+                // now, A[3k+m...3(k+1)+m] with m=((A >> 3k) % 8)^mask is also used by iteration k
                 // and must therefore also be completed
-                let m = ((bits_p1 >> 3 * k) % 8)^1;
+                let m = ((bits_p1 >> 3 * k) % 8)^mask;
                 let rem_bits_p2 = (3 + m) - (bit_count_p1 - 3 * k).min(3 + m);
                 let bit_count_p2 = bit_count_p1.max(3 * (k + 1) + m);
                 
                 for bits_p2_comp in 0..(1 << rem_bits_p2) {
                     let bits_p2 = (bits_p2_comp << bit_count_p1) | bits_p1;
                     
-                    let bits_p2_output = eval_program(&code, &[bits_p2, 0, 0], |_| {});
+                    // the bits above the guessed ones are unknown, a sentinel bit keeps the program
+                    // running past iteration k without affecting the bits read up to it
+                    let bits_p2_output = eval_program(&code, &[bits_p2 | (1 << bit_count_p2), 0, 0], |_| {});
                     
-                    if k < bits_p2_output.len() && bits_p2_output.len() <= code.len() && (0..=k).all(|i| bits_p2_output[i] == code[i]) {
+                    if bits_p2 >> (3 * code.len()) == 0 && (0..=k).all(|i| bits_p2_output[i] == code[i]) {
                         next_nodes.push((bits_p2, bit_count_p2));
                     }
                 }
@@ -127,8 +134,13 @@ pub fn part_2(data: File) -> usize {
         }
         
         swap(&mut nodes, &mut next_nodes);
+        debug!(target: "day17", "Iteration {k}: {} candidates", nodes.len());
+        stats::record_n("day17", "candidates_per_iteration", k, nodes.len());
         k += 1;
     }
     
-    return nodes.into_iter().map(|(a, _)| a).min().unwrap();
+    return nodes.into_iter()
+        .map(|(a, _)| a)
+        .filter(|&a| eval_program(&code, &[a, 0, 0], |_| {}) == code)
+        .min().unwrap();
 }
\ No newline at end of file
@@ -1,49 +1,100 @@
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::iter::once;
-use itertools::Itertools;
-
-fn is_safe(mut numbers: impl Iterator<Item=usize>) -> bool {
-    if let Some((first, second)) = numbers.next_tuple() {
-        let diff = second as isize - first as isize;
-        if diff < -3 || diff > 3 || diff == 0 { return false; }
-        return is_safe_directed(once(second).chain(numbers), diff > 0, 0);
-    } else {
-        return true;
-    }
-}
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::RangeInclusive;
 
-fn is_safe_directed(
-    numbers: impl Iterator<Item=usize>,
+// Returns the minimal set of level indices whose removal makes the report monotone in the given
+// direction with every step within the bounds, if at most max_removals levels need to be removed.
+fn repair_directed(
+    levels: &[usize],
+    max_removals: usize,
+    steps: &RangeInclusive<usize>,
     upwards: bool,
-    max_mistakes: usize,
-) -> bool {
-    numbers.tuple_windows()
-        .map(|(a, b)| (upwards && a < b && b <= a + 3) || (!upwards && b < a && a <= b + 3))
-        .filter(|safe| !safe)
-        .nth(max_mistakes)
-        .is_none()
+) -> Option<Vec<usize>> {
+    let n = levels.len();
+    let valid = |a: usize, b: usize| if upwards { b >= a && steps.contains(&(b - a)) } else { a >= b && steps.contains(&(a - b)) };
+
+    // removals[i] is the minimal number of removals among the first i levels such that level i is kept,
+    // only the predecessors within max_removals + 1 levels can lead to a feasible repair
+    let mut removals = vec![usize::MAX; n];
+    let mut parent = vec![None; n];
+
+    for i in 0..n {
+        if i <= max_removals {
+            removals[i] = i;
+        }
+
+        for j in i.saturating_sub(max_removals + 1)..i {
+            if removals[j] != usize::MAX && valid(levels[j], levels[i]) {
+                let total = removals[j] + (i - j - 1);
+
+                if total < removals[i] {
+                    removals[i] = total;
+                    parent[i] = Some(j);
+                }
+            }
+        }
+    }
+
+    let last = (n.saturating_sub(max_removals + 1)..n)
+        .filter(|&i| removals[i] != usize::MAX)
+        .min_by_key(|&i| removals[i] + (n - 1 - i))
+        .filter(|&i| removals[i] + (n - 1 - i) <= max_removals)?;
+
+    let mut kept = vec![false; n];
+    let mut curr = Some(last);
+    while let Some(i) = curr {
+        kept[i] = true;
+        curr = parent[i];
+    }
+
+    return Some((0..n).filter(|&i| !kept[i]).collect());
 }
 
-fn is_safe_except_one(numbers: impl Iterator<Item=usize>) -> bool {
-    let numbers: Vec<usize> = numbers.collect();
-    return (0..numbers.len()).any(
-        |j| is_safe(
-            numbers.iter().enumerate().filter(|(i, _)| *i != j).map(|(_, &x)| x)
-        )
-    )
+// Returns the indices of the levels to remove such that the report becomes safe, using O(n k) time for
+// k = max_removals, or None if more than max_removals levels would need to be removed.
+pub fn repair(levels: &[usize], max_removals: usize, steps: RangeInclusive<usize>) -> Option<Vec<usize>> {
+    if levels.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let upwards = repair_directed(levels, max_removals, &steps, true);
+    let downwards = repair_directed(levels, max_removals, &steps, false);
+
+    return match (upwards, downwards) {
+        (Some(a), Some(b)) => Some(if b.len() < a.len() { b } else { a }),
+        (a, b) => a.or(b),
+    };
 }
 
-pub fn part_1(fs: File) -> usize {
+fn count_safe(fs: File, max_removals: usize) -> usize {
     return BufReader::new(fs)
         .lines().flatten()
-        .map(|line| is_safe(line.split_ascii_whitespace().map(|val| val.parse::<usize>().unwrap())))
-        .fold(0, |prev, safe| if safe { prev + 1 } else { prev });
+        .map(|line| line.split_ascii_whitespace().map(|val| val.parse::<usize>().unwrap()).collect::<Vec<_>>())
+        .filter(|levels| repair(levels, max_removals, 1..=3).is_some())
+        .count();
+}
+
+pub fn part_1(fs: File) -> usize {
+    return count_safe(fs, 0);
 }
 
 pub fn part_2(fs: File) -> usize {
-    return BufReader::new(fs)
-        .lines().flatten()
-        .map(|line| is_safe_except_one(line.split_ascii_whitespace().map(|val| val.parse::<usize>().unwrap())))
-        .fold(0, |prev, safe| if safe { prev + 1 } else { prev });
-}
\ No newline at end of file
+    return count_safe(fs, 1);
+}
+
+pub fn write_report(fs: File, max_removals: usize, steps: RangeInclusive<usize>, out: &mut impl Write) -> io::Result<()> {
+    for (i, line) in BufReader::new(fs).lines().flatten().enumerate() {
+        let levels = line.split_ascii_whitespace().map(|val| val.parse::<usize>().unwrap()).collect::<Vec<_>>();
+
+        match repair(&levels, max_removals, steps.clone()) {
+            Some(removed) if removed.is_empty() => writeln!(out, "report {i} safe")?,
+            Some(removed) => writeln!(
+                out, "report {i} safe after removing {:?} at {removed:?}",
+                removed.iter().map(|&j| levels[j]).collect::<Vec<_>>(),
+            )?,
+            None => writeln!(out, "report {i} unsafe")?,
+        }
+    }
+
+    return Ok(());
+}
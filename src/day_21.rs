@@ -3,6 +3,7 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use log::trace;
 
 #[derive(Hash, Eq, PartialEq, Debug, Copy, Clone)]
 enum DirButton { Left, Right, Up, Down, Enter }
@@ -167,7 +168,7 @@ impl KeypadButton {
             }
         }
 
-        // println!("{:?} to {:?}; {:?}", self, other, seqs);
+        trace!(target: "day21", "{:?} to {:?}; {:?}", self, other, seqs);
 
         return seqs;
     }
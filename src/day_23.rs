@@ -3,6 +3,8 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::mem::swap;
 use itertools::{Itertools};
+use log::debug;
+use crate::stats;
 
 pub fn part_1(data: File) -> usize {
     let get_id = |name: &str| name.chars().enumerate().map(
@@ -97,6 +99,9 @@ pub fn part_2(data: File) -> String {
     let mut next = HashSet::new();
 
     while cliques.len() > 1 {
+        debug!(target: "day23", "{} cliques of size {}", cliques.len(), cliques.iter().next().unwrap().len());
+        stats::record_n("day23", "cliques_per_size", cliques.iter().next().unwrap().len(), cliques.len());
+
         for clique in cliques.drain() {
             let v = clique.iter().next().unwrap().clone();
 
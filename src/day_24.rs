@@ -6,6 +6,7 @@ use petgraph::{Direction, Graph};
 use petgraph::graph::{DiGraph, NodeIndex};
 use bimap::BiMap;
 use petgraph::visit::{Bfs, Reversed, Topo};
+use log::{debug, warn};
 
 #[derive(Hash, PartialEq, Eq, Debug, Ord, PartialOrd, Clone, Copy)]
 enum GateVariant { And, Or, Xor }
@@ -27,7 +28,7 @@ enum Node {
 }
 
 #[derive(Debug, Clone)]
-struct Circuit {
+pub struct Circuit {
     graph: DiGraph<Node, ()>,
 
     interpreted_inputs: HashMap<String, Vec<NodeIndex>>,
@@ -39,7 +40,15 @@ struct Circuit {
 }
 
 impl Circuit {
-    fn execute(&self, input: &HashMap<NodeIndex, bool>) -> HashMap<String, bool> {
+    pub fn test_input(&self) -> &HashMap<NodeIndex, bool> {
+        return &self.test_input;
+    }
+
+    pub fn output_bits(&self) -> usize {
+        return self.outputs.len();
+    }
+
+    pub fn execute(&self, input: &HashMap<NodeIndex, bool>) -> HashMap<String, bool> {
         let mut state = HashMap::new();
         self.inputs.iter().for_each(|idx| { state.insert(idx.clone(), input[idx].clone()); });
 
@@ -75,7 +84,7 @@ impl Circuit {
         );
     }
 
-    fn convert_output(&self, output: &HashMap<String, bool>) -> usize {
+    pub fn convert_output(&self, output: &HashMap<String, bool>) -> usize {
         return self.outputs.iter()
             .map(|k| self.labels.get_by_left(k).unwrap())
             .enumerate()
@@ -83,7 +92,7 @@ impl Circuit {
             .sum::<usize>();
     }
 
-    fn convert_input(&self, xs: &HashMap<String, usize>) -> HashMap<NodeIndex, bool> {
+    pub fn convert_input(&self, xs: &HashMap<String, usize>) -> HashMap<NodeIndex, bool> {
         let mut input = HashMap::new();
 
         for (name, digits) in self.interpreted_inputs.iter() {
@@ -103,7 +112,7 @@ impl Circuit {
     }
 }
 
-fn compile_circuit(
+pub fn compile_circuit(
     data: File,
 ) -> Circuit {
     let mut lines = BufReader::new(data).lines().flatten();
@@ -201,7 +210,7 @@ pub fn part_1(data: File) -> usize {
     return circuit.convert_output(&state);
 }
 
-fn swap_circuit_registers(
+pub fn swap_circuit_registers(
     circuit: &mut Circuit,
     a: &str,
     b: &str,
@@ -263,7 +272,7 @@ pub fn part_2(data: File) -> String {
 
     let output_bits = circuit.outputs.len();
 
-    println!("Input {input_bits} Output {output_bits}");
+    debug!(target: "day24", "Input {input_bits} Output {output_bits}");
 
     for i in 0..input_bits {
         let x: usize = 1 << i;
@@ -279,7 +288,7 @@ pub fn part_2(data: File) -> String {
         let expected = (x + y) & ((1 << output_bits) - 1);
 
         if z != expected {
-            println!("{i} X={x} Y={y} Z={z}={z:b} != {expected}={expected:b}");
+            warn!(target: "day24", "{i} X={x} Y={y} Z={z}={z:b} != {expected}={expected:b}");
         }
     }
 
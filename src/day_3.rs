@@ -1,131 +1,305 @@
+use std::fmt::{Display, Formatter};
 use std::fs::File;
+use std::io::{self, Write};
 use utf8_read::Reader;
 
-enum MulParserState {
-    Left(usize),
-    ArgLeft,
-    ArgRight,
-    Failed,
-    Finished(usize, usize),
+#[derive(Clone, Copy, Debug)]
+pub enum InstructionKind {
+    Enable,
+    Disable,
+    Value(fn(&[usize]) -> usize),
 }
 
-struct MulParser {
-    state: MulParserState,
-    left: usize,
-    right: usize
+#[derive(Clone, Debug)]
+pub struct InstructionSpec {
+    pub name: &'static str,
+    pub arity: usize,
+    // maximal number of digits of every argument, arguments always require at least one digit
+    pub max_digits: usize,
+    pub kind: InstructionKind,
 }
 
-impl MulParser {
-    fn new() -> MulParser {
-        MulParser { left: 0, right: 0, state: MulParserState::Left(0) }
+// the puzzle only allows arguments of 1 to 3 digits
+pub const MAX_DIGITS: usize = 3;
+
+// the largest digit limit for which neither an argument nor the product of two arguments overflows
+pub const MAX_DIGIT_LIMIT: usize = usize::MAX.ilog10() as usize / 2;
+
+impl InstructionSpec {
+    pub fn mul(max_digits: usize) -> InstructionSpec {
+        assert!(max_digits <= MAX_DIGIT_LIMIT, "Digit limit {max_digits} exceeds {MAX_DIGIT_LIMIT}");
+        return InstructionSpec { name: "mul", arity: 2, max_digits, kind: InstructionKind::Value(|args| args[0] * args[1]) };
     }
 
-    fn advance(&mut self, x: char) {
-        self.state = match &self.state {
-            MulParserState::Left(i) => match (i, x) {
-                (0, 'm') => MulParserState::Left(1),
-                (1, 'u') => MulParserState::Left(2),
-                (2, 'l') => MulParserState::Left(3),
-                (3, '(') => MulParserState::ArgLeft,
-                (_, _) => MulParserState::Failed
-            },
-            MulParserState::ArgLeft => if x == ',' { MulParserState::ArgRight } else {
-                match x.to_digit(10) {
-                    Some(x) => {
-                        self.left = self.left * 10 + x as usize;
-                        MulParserState::ArgLeft
-                    },
-                    None => MulParserState::Failed
-                }
-            },
-            MulParserState::ArgRight => if x == ')' { MulParserState::Finished(self.left, self.right) } else {
-                match x.to_digit(10) {
-                    Some(x) => {
-                        self.right = self.right * 10 + x as usize;
-                        MulParserState::ArgRight
-                    },
-                    None => MulParserState::Failed
-                }
-            },
-            MulParserState::Finished(_, _) => MulParserState::Failed,
-            MulParserState::Failed => MulParserState::Failed,
-        };
+    pub fn enable() -> InstructionSpec {
+        return InstructionSpec { name: "do", arity: 0, max_digits: 0, kind: InstructionKind::Enable };
     }
 
-    fn reset(&mut self) {
-        self.state = MulParserState::Left(0);
-        self.left = 0;
-        self.right = 0;
+    pub fn disable() -> InstructionSpec {
+        return InstructionSpec { name: "don't", arity: 0, max_digits: 0, kind: InstructionKind::Disable };
     }
+}
 
-    fn advance_or_reset(&mut self, x: char) {
-        self.advance(x);
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub name: &'static str,
+    pub args: Vec<usize>,
+    // byte offset of the first character of the instruction
+    pub offset: usize,
+    // whether the instructions were enabled when this instruction was reached
+    pub enabled: bool,
+    pub value: Option<usize>,
+}
 
-        if let MulParserState::Failed = &self.state {
-            self.reset();
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectReason {
+    Whitespace,
+    TooManyDigits(usize),
+    EmptyArgument,
+    MissingArguments,
+    ExtraArguments,
+    UnexpectedCharacter(char),
+    Unterminated,
+}
 
-            self.advance(x);
-            if let MulParserState::Failed = &self.state { self.reset(); }
-        }
+impl Display for RejectReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            RejectReason::Whitespace => write!(f, "whitespace"),
+            RejectReason::TooManyDigits(limit) => write!(f, "more than {limit} digits"),
+            RejectReason::EmptyArgument => write!(f, "empty argument"),
+            RejectReason::MissingArguments => write!(f, "missing arguments"),
+            RejectReason::ExtraArguments => write!(f, "too many arguments"),
+            RejectReason::UnexpectedCharacter(x) => write!(f, "unexpected {x:?}"),
+            RejectReason::Unterminated => write!(f, "unterminated"),
+        };
     }
 }
 
-pub fn part_1(data: File) -> usize {
-    let mut parser = MulParser::new();
-    let mut total = 0;
+// An instruction name that was not followed by a valid argument list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NearMiss {
+    pub name: &'static str,
+    pub offset: usize,
+    // the text from the instruction name up to and including the rejected character
+    pub text: String,
+    pub reason: RejectReason,
+}
 
-    for x in Reader::new(&data).into_iter().flatten() {
-        parser.advance_or_reset(x.clone());
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Instruction(Instruction),
+    NearMiss(NearMiss),
+}
 
-        if let MulParserState::Finished(x, y) = &parser.state {
-            total += x * y;
-            parser.reset();
-        }
-    }
+#[derive(Clone, Copy, Debug)]
+enum CandidateState {
+    Name(usize),
+    Open,
+    Arg(usize, usize),
+}
 
-    return total;
+#[derive(Clone, Debug)]
+struct Candidate {
+    spec: usize,
+    offset: usize,
+    state: CandidateState,
+    args: Vec<usize>,
+    text: String,
 }
 
+enum CandidateStep {
+    Pending(Candidate),
+    Finished(Candidate),
+    Rejected(Candidate, RejectReason),
+    Discarded,
+}
 
-pub fn part_2(data: File) -> usize {
-    let mut parser = MulParser::new();
-    let mut total = 0;
+// Recognises the instructions of the table within corrupted memory. Every character might start
+// an instruction, hence all partial matches are advanced in parallel, such that overlapping
+// candidates like "mumul(1,2)" or "do()" within "don't()" are handled without backtracking.
+pub struct Interpreter {
+    specs: Vec<InstructionSpec>,
+    candidates: Vec<Candidate>,
+    next_candidates: Vec<Candidate>,
+    offset: usize,
+    enabled: bool,
+    diagnostics: bool,
+}
 
-    let mut do_state = 0;
-    let mut dont_state = 0;
+impl Interpreter {
+    pub fn new(specs: Vec<InstructionSpec>) -> Interpreter {
+        return Interpreter {
+            specs, candidates: Vec::new(), next_candidates: Vec::new(), offset: 0, enabled: true, diagnostics: false,
+        };
+    }
 
-    let mut active = true;
+    // Additionally emits a near miss for every complete instruction name whose argument list is rejected.
+    pub fn with_diagnostics(mut self, diagnostics: bool) -> Interpreter {
+        self.diagnostics = diagnostics;
+        return self;
+    }
 
-    for x in Reader::new(&data).into_iter().flatten() {
-        if x == "do()"[do_state..=do_state].chars().next().unwrap() {
-            do_state += 1;
+    fn advance_candidate(spec: &InstructionSpec, mut candidate: Candidate, x: char) -> CandidateStep {
+        if let CandidateState::Name(i) = candidate.state {
+            if !spec.name[i..].starts_with(x) { return CandidateStep::Discarded; }
+
+            candidate.state = if i + x.len_utf8() == spec.name.len() {
+                candidate.text.push_str(spec.name);
+                CandidateState::Open
+            } else {
+                CandidateState::Name(i + x.len_utf8())
+            };
+
+            return CandidateStep::Pending(candidate);
+        }
+
+        candidate.text.push(x);
 
-            if do_state == 4 {
-                do_state = 0;
-                active = true;
+        let next = match candidate.state {
+            CandidateState::Name(_) => unreachable!(),
+            CandidateState::Open => match x {
+                '(' if spec.arity == 0 => Ok(CandidateState::Arg(0, 0)),
+                '(' => { candidate.args.push(0); Ok(CandidateState::Arg(0, 0)) },
+                _ if x.is_whitespace() => Err(RejectReason::Whitespace),
+                // the name is merely the prefix of a longer word such as "do" in "don't"
+                _ if x.is_alphabetic() => { return CandidateStep::Discarded; },
+                _ => Err(RejectReason::UnexpectedCharacter(x)),
+            },
+            CandidateState::Arg(i, digits) => match (x, x.to_digit(10)) {
+                (')', _) if spec.arity == 0 || (i + 1 == spec.arity && digits > 0) => {
+                    return CandidateStep::Finished(candidate);
+                },
+                (',', _) if i + 1 < spec.arity && digits > 0 => {
+                    candidate.args.push(0);
+                    Ok(CandidateState::Arg(i + 1, 0))
+                },
+                (')' | ',', _) if spec.arity > 0 && digits == 0 => Err(RejectReason::EmptyArgument),
+                (')', _) => Err(RejectReason::MissingArguments),
+                (',', _) => Err(RejectReason::ExtraArguments),
+                (_, Some(_)) if spec.arity == 0 => Err(RejectReason::ExtraArguments),
+                (_, Some(_)) if digits == spec.max_digits => Err(RejectReason::TooManyDigits(spec.max_digits)),
+                (_, Some(d)) => {
+                    candidate.args[i] = candidate.args[i] * 10 + d as usize;
+                    Ok(CandidateState::Arg(i, digits + 1))
+                },
+                _ if x.is_whitespace() => Err(RejectReason::Whitespace),
+                _ => Err(RejectReason::UnexpectedCharacter(x)),
+            },
+        };
+
+        return match next {
+            Ok(state) => { candidate.state = state; CandidateStep::Pending(candidate) },
+            Err(reason) => CandidateStep::Rejected(candidate, reason),
+        };
+    }
+
+    fn near_miss(&self, candidate: Candidate, reason: RejectReason) -> NearMiss {
+        return NearMiss { name: self.specs[candidate.spec].name, offset: candidate.offset, text: candidate.text, reason };
+    }
+
+    // Advances the interpreter by one character, emitting the instructions that end with it.
+    pub fn advance(&mut self, x: char, mut emit: impl FnMut(Event)) {
+        for spec in 0..self.specs.len() {
+            self.candidates.push(Candidate {
+                spec, offset: self.offset, state: CandidateState::Name(0), args: Vec::new(), text: String::new(),
+            });
+        }
+
+        let mut candidates = std::mem::take(&mut self.candidates);
+
+        for candidate in candidates.drain(..) {
+            let spec = &self.specs[candidate.spec];
+
+            match Interpreter::advance_candidate(spec, candidate, x) {
+                CandidateStep::Pending(candidate) => self.next_candidates.push(candidate),
+                CandidateStep::Finished(finished) => {
+                    let value = match spec.kind {
+                        InstructionKind::Enable => { self.enabled = true; None },
+                        InstructionKind::Disable => { self.enabled = false; None },
+                        InstructionKind::Value(f) => Some(f(&finished.args)),
+                    };
+
+                    emit(Event::Instruction(Instruction {
+                        name: spec.name, args: finished.args, offset: finished.offset, enabled: self.enabled, value,
+                    }));
+                },
+                CandidateStep::Rejected(candidate, reason) => if self.diagnostics {
+                    emit(Event::NearMiss(self.near_miss(candidate, reason)));
+                },
+                CandidateStep::Discarded => {},
             }
-        } else {
-            do_state = 0;
         }
 
-        if x == "don't()"[dont_state..=dont_state].chars().next().unwrap() {
-            dont_state += 1;
+        self.candidates = std::mem::replace(&mut self.next_candidates, candidates);
+        self.offset += x.len_utf8();
+    }
 
-            if dont_state == 7 {
-                dont_state = 0;
-                active = false;
+    // Ends the input, reporting the candidates that were cut off as near misses.
+    pub fn finish(mut self, mut emit: impl FnMut(Event)) {
+        if self.diagnostics {
+            for candidate in std::mem::take(&mut self.candidates) {
+                if !matches!(candidate.state, CandidateState::Name(_)) {
+                    emit(Event::NearMiss(self.near_miss(candidate, RejectReason::Unterminated)));
+                }
             }
-        } else {
-            dont_state = 0;
         }
+    }
+}
 
-        parser.advance_or_reset(x.clone());
+// Runs the instructions of the table found in the memory, returning the sum of all enabled values.
+pub fn interpret(data: File, specs: Vec<InstructionSpec>, diagnostics: bool, mut emit: impl FnMut(&Event)) -> usize {
+    let mut interpreter = Interpreter::new(specs).with_diagnostics(diagnostics);
+    let mut total = 0;
 
-        if let MulParserState::Finished(x, y) = &parser.state {
-            if active { total += x * y };
-            parser.reset();
+    let mut handle = |event: Event| {
+        if let Event::Instruction(Instruction { enabled: true, value: Some(value), .. }) = &event {
+            total += value;
         }
+
+        emit(&event);
+    };
+
+    for x in Reader::new(&data).into_iter().flatten() {
+        interpreter.advance(x, &mut handle);
     }
+    interpreter.finish(&mut handle);
 
     return total;
-}
\ No newline at end of file
+}
+
+pub fn part_1(data: File) -> usize {
+    return interpret(data, vec![InstructionSpec::mul(MAX_DIGITS)], false, |_| {});
+}
+
+pub fn part_2(data: File) -> usize {
+    return interpret(
+        data, vec![InstructionSpec::mul(MAX_DIGITS), InstructionSpec::enable(), InstructionSpec::disable()], false, |_| {},
+    );
+}
+
+pub fn write_report(data: File, max_digits: usize, near_misses: bool, out: &mut impl Write) -> io::Result<()> {
+    let specs = vec![InstructionSpec::mul(max_digits), InstructionSpec::enable(), InstructionSpec::disable()];
+    let mut result = Ok(());
+
+    let total = interpret(data, specs, near_misses, |event| {
+        if result.is_ok() {
+            result = match event {
+                Event::Instruction(instruction) => writeln!(
+                    out, "{} {}({}){}{}",
+                    instruction.offset,
+                    instruction.name,
+                    instruction.args.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(","),
+                    instruction.value.map_or(String::new(), |x| format!(" = {x}")),
+                    if instruction.enabled { "" } else { " disabled" },
+                ),
+                Event::NearMiss(near_miss) => writeln!(
+                    out, "{} rejected {:?}: {}", near_miss.offset, near_miss.text, near_miss.reason,
+                ),
+            };
+        }
+    });
+    result?;
+
+    return writeln!(out, "total {total}");
+}
@@ -1,142 +1,261 @@
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
+use aho_corasick::AhoCorasick;
+use crate::common::Matrix;
+
+pub const DIRECTIONS: [(&str, (isize, isize)); 8] = [
+    ("E", (1, 0)), ("SE", (1, 1)), ("S", (0, 1)), ("SW", (-1, 1)),
+    ("W", (-1, 0)), ("NW", (-1, -1)), ("N", (0, -1)), ("NE", (1, -1)),
+];
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchOptions {
+    // words may continue on the opposite side of the grid
+    pub wrap: bool,
+    // matches may share cells, otherwise matches are kept greedily in the order they are found
+    pub overlaps: bool,
+}
 
-#[derive(Copy, Clone, Debug)]
-struct PatternCounter<const N: usize> {
-    pattern: [char; N],
-    count: usize,
-    current: usize,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WordMatch {
+    pub word: usize,
+    pub start: (isize, isize),
+    pub direction: (isize, isize),
 }
 
-impl<const N: usize> PatternCounter<N> {
-    fn new(pattern: [char; N]) -> PatternCounter<N> {
-        PatternCounter {
-            count: 0,
-            current: 0,
-            pattern,
-        }
+impl WordMatch {
+    pub fn cells<'a>(&self, grid: &'a Matrix<char>, len: usize) -> impl Iterator<Item=usize> + 'a {
+        let (w, h) = (grid.width as isize, grid.height as isize);
+        let (start, direction) = (self.start, self.direction);
+
+        return (0..len as isize).map(move |k| {
+            let p = (start.0 + k * direction.0, start.1 + k * direction.1);
+            return grid.point_to_index((p.0.rem_euclid(w), p.1.rem_euclid(h))).unwrap();
+        });
     }
+}
 
-    fn advance(&mut self, x: char) -> bool {
-        if self.pattern[self.current] == x {
-            if self.current == N - 1 {
-                self.count += 1;
-                self.current = if self.pattern[0] == x { 1 } else { 0 };
+pub fn read_grid(data: File) -> Matrix<char> {
+    return Matrix::from_lines(BufReader::new(data).lines().flatten(), |x, _| x);
+}
 
-                return true;
-            } else {
-                self.current += 1;
+// Returns the lines of cell indices in the given direction together with the number of cells a match
+// may start at. Without wrap-around a line runs from border to border, with wrap-around a line is a
+// cycle extended by enough cells for matches to cross the border.
+fn lines(grid: &Matrix<char>, direction: (isize, isize), wrap: bool, max_len: usize) -> Vec<(Vec<usize>, usize)> {
+    let (w, h) = (grid.width as isize, grid.height as isize);
+    let mut visited = vec![false; grid.buffer.len()];
+    let mut result = Vec::new();
+
+    for i in 0..grid.buffer.len() {
+        let (x, y) = grid.index_to_point(i).unwrap();
+
+        if wrap {
+            if visited[i] { continue; }
+
+            let mut cycle = Vec::new();
+            let mut p = (x, y);
+            loop {
+                let j = grid.point_to_index(p).unwrap();
+                if visited[j] { break; }
+
+                visited[j] = true;
+                cycle.push(j);
+                p = ((p.0 + direction.0).rem_euclid(w), (p.1 + direction.1).rem_euclid(h));
             }
-        } else {
-            self.current = if self.pattern[0] == x { 1 } else { 0 };
-        }
 
-        return false;
-    }
+            let len = cycle.len();
+            let extended = cycle.iter().cycle().take(len + max_len.saturating_sub(1)).cloned().collect();
+            result.push((extended, len));
+        } else if grid.point_to_index((x - direction.0, y - direction.1)).is_none() {
+            let mut line = Vec::new();
+            let mut p = (x, y);
+            while let Some(j) = grid.point_to_index(p) {
+                line.push(j);
+                p = (p.0 + direction.0, p.1 + direction.1);
+            }
 
-    fn restart(&mut self) {
-        self.current = 0;
+            let len = line.len();
+            result.push((line, len));
+        }
     }
-}
 
-const LINE_LENGTH: usize = 140;
+    return result;
+}
 
-pub fn part_1(data: File) -> usize {
-    const PATTERN: [char; 4] = ['X', 'M', 'A', 'S'];
-    const PATTERN_REV: [char; 4] = ['S', 'A', 'M', 'X'];
+// Finds every occurrence of the words in all eight directions, using a single automaton for all words.
+pub fn search(grid: &Matrix<char>, words: &[&str], options: SearchOptions) -> Vec<WordMatch> {
+    let automaton = AhoCorasick::new(words).unwrap();
+    let max_len = words.iter().map(|word| word.chars().count()).max().unwrap_or(0);
+    let mut matches = Vec::new();
 
-    let mut horizontal_forward = PatternCounter::new(PATTERN);
-    let mut horizontal_backward = PatternCounter::new(PATTERN_REV);
+    for (_, direction) in DIRECTIONS {
+        for (line, starts) in lines(grid, direction, options.wrap, max_len) {
+            let mut text = String::new();
+            let mut char_index = Vec::new();
 
-    let mut vertical_forward = [PatternCounter::new(PATTERN); LINE_LENGTH];
-    let mut vertical_backward = [PatternCounter::new(PATTERN_REV); LINE_LENGTH];
+            for (k, &j) in line.iter().enumerate() {
+                char_index.extend(std::iter::repeat_n(k, grid.buffer[j].len_utf8()));
+                text.push(grid.buffer[j]);
+            }
 
-    let mut diag_forward = [PatternCounter::new(PATTERN); LINE_LENGTH];
-    let mut diag_backward = [PatternCounter::new(PATTERN_REV); LINE_LENGTH];
-    let mut diag_base = LINE_LENGTH;
+            for m in automaton.find_overlapping_iter(&text) {
+                let k = char_index[m.start()];
 
-    let mut diag2_forward = [PatternCounter::new(PATTERN); LINE_LENGTH];
-    let mut diag2_backward = [PatternCounter::new(PATTERN_REV); LINE_LENGTH];
-    let mut diag2_base = 0;
+                if k < starts {
+                    let start = grid.index_to_point(line[k]).unwrap();
+                    matches.push(WordMatch { word: m.pattern().as_usize(), start, direction });
+                }
+            }
+        }
+    }
 
-    for line in BufReader::new(data).lines().flatten() {
-        assert_eq!(line.len(), LINE_LENGTH);
+    if !options.overlaps {
+        let mut used = vec![false; grid.buffer.len()];
 
-        for (i, x) in line.chars().enumerate() {
-            horizontal_forward.advance(x);
-            horizontal_backward.advance(x);
-            vertical_forward[i].advance(x);
-            vertical_backward[i].advance(x);
+        matches.retain(|m| {
+            let cells = m.cells(grid, words[m.word].chars().count()).collect::<Vec<_>>();
+            if cells.iter().any(|&j| used[j]) { return false; }
 
-            diag_forward[(diag_base + i) % LINE_LENGTH].advance(x);
-            diag_backward[(diag_base + i) % LINE_LENGTH].advance(x);
+            cells.into_iter().for_each(|j| used[j] = true);
+            return true;
+        });
+    }
 
-            diag2_forward[(diag2_base + i) % LINE_LENGTH].advance(x);
-            diag2_backward[(diag2_base + i) % LINE_LENGTH].advance(x);
-        }
+    return matches;
+}
 
-        diag_base = if diag_base == 1 { LINE_LENGTH } else { diag_base - 1 };
-        diag_forward[diag_base % LINE_LENGTH].restart();
-        diag_backward[diag_base % LINE_LENGTH].restart();
+pub fn part_1(data: File) -> usize {
+    return search(&read_grid(data), &["XMAS"], SearchOptions { wrap: false, overlaps: true }).len();
+}
 
-        diag2_forward[diag2_base % LINE_LENGTH].restart();
-        diag2_backward[diag2_base % LINE_LENGTH].restart();
-        diag2_base = if diag2_base == LINE_LENGTH - 1 { 0 } else { diag2_base + 1 };
+pub fn write_report(data: File, words: &[&str], options: SearchOptions, out: &mut impl Write) -> io::Result<()> {
+    let matches = search(&read_grid(data), words, options);
 
-        horizontal_forward.restart();
-        horizontal_backward.restart();
+    for m in matches.iter() {
+        let direction = DIRECTIONS.iter().find(|(_, d)| *d == m.direction).unwrap().0;
+        writeln!(out, "{} at {:?} {direction}", words[m.word], m.start)?;
     }
 
-    return horizontal_forward.count
-        + horizontal_backward.count
-        + vertical_forward.into_iter().map(|x| x.count).sum::<usize>()
-        + vertical_backward.into_iter().map(|x| x.count).sum::<usize>()
-        + diag_forward.into_iter().map(|x| x.count).sum::<usize>()
-        + diag_backward.into_iter().map(|x| x.count).sum::<usize>()
-        + diag2_forward.into_iter().map(|x| x.count).sum::<usize>()
-        + diag2_backward.into_iter().map(|x| x.count).sum::<usize>();
+    return writeln!(out, "total {}", matches.len());
 }
 
-pub fn part_2(data: File) -> usize {
-    const PATTERN: [char; 3] = ['M', 'A', 'S'];
-    const PATTERN_REV: [char; 3] = ['S', 'A', 'M'];
+// A rectangular grid of characters, where None matches any character.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    pub cells: Vec<Option<char>>,
+    pub width: usize,
+    pub height: usize,
+}
 
-    let mut diag_forward = [PatternCounter::new(PATTERN); LINE_LENGTH];
-    let mut diag_backward = [PatternCounter::new(PATTERN_REV); LINE_LENGTH];
-    let mut diag_base = LINE_LENGTH;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TemplateMatch {
+    // the top left corner of the matched region
+    pub position: (isize, isize),
+    // the index into the variants of the template
+    pub variant: usize,
+}
 
-    let mut diag2_forward = [PatternCounter::new(PATTERN); LINE_LENGTH];
-    let mut diag2_backward = [PatternCounter::new(PATTERN_REV); LINE_LENGTH];
-    let mut diag2_base = 0;
+impl Template {
+    // Parses rows separated by '/', with '.' as wildcard, e.g. "M.S/.A./M.S".
+    pub fn parse(template: &str) -> Template {
+        let rows = template.split('/').collect::<Vec<_>>();
+        let width = rows[0].chars().count();
+        assert!(rows.iter().all(|row| row.chars().count() == width), "Template rows differ in length");
 
-    let mut total = 0;
+        let cells = rows.iter().flat_map(|row| row.chars().map(|x| if x == '.' { None } else { Some(x) })).collect();
+        return Template { cells, width, height: rows.len() };
+    }
 
-    for line in BufReader::new(data).lines().flatten() {
-        assert_eq!(line.len(), LINE_LENGTH);
+    fn get(&self, x: usize, y: usize) -> Option<char> {
+        return self.cells[y * self.width + x];
+    }
 
-        let mut diag2_check = [false, false];
-        for (i, x) in line.chars().enumerate() {
-            let diag_curr = diag_forward[(diag_base + i) % LINE_LENGTH].advance(x)
-                | diag_backward[(diag_base + i) % LINE_LENGTH].advance(x);
+    fn rotated(&self) -> Template {
+        let cells = (0..self.width)
+            .flat_map(|y| (0..self.height).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(y, self.height - 1 - x))
+            .collect();
+        return Template { cells, width: self.height, height: self.width };
+    }
+
+    fn reflected(&self) -> Template {
+        let cells = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(self.width - 1 - x, y))
+            .collect();
+        return Template { cells, width: self.width, height: self.height };
+    }
 
-            let diag2_curr = diag2_forward[(diag2_base + i) % LINE_LENGTH].advance(x)
-                | diag2_backward[(diag2_base + i) % LINE_LENGTH].advance(x);
+    // Returns the distinct rotations and reflections of the template.
+    pub fn variants(&self) -> Vec<Template> {
+        let mut variants: Vec<Template> = Vec::new();
+        let mut curr = self.clone();
 
-            if diag_curr && diag2_check[0] {
-                total += 1;
+        for _ in 0..4 {
+            for variant in [curr.clone(), curr.reflected()] {
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
             }
+            curr = curr.rotated();
+        }
 
-            diag2_check = [diag2_check[1], diag2_curr];
+        return variants;
+    }
+
+    fn matches_at(&self, grid: &Matrix<char>, (x0, y0): (isize, isize)) -> bool {
+        return (0..self.height).all(|y| (0..self.width).all(|x| match self.get(x, y) {
+            Some(c) => grid.get((x0 + x as isize, y0 + y as isize)) == Some(&c),
+            None => true,
+        }));
+    }
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height {
+            if y > 0 { write!(f, "/")?; }
+            for x in 0..self.width {
+                write!(f, "{}", self.get(x, y).unwrap_or('.'))?;
+            }
         }
 
-        diag_base = if diag_base == 1 { LINE_LENGTH } else { diag_base - 1 };
-        diag_forward[diag_base % LINE_LENGTH].restart();
-        diag_backward[diag_base % LINE_LENGTH].restart();
+        return Ok(());
+    }
+}
+
+// Finds all occurrences of any rotation or reflection of the template.
+pub fn match_template(grid: &Matrix<char>, template: &Template) -> Vec<TemplateMatch> {
+    let mut matches = Vec::new();
 
-        diag2_forward[diag2_base % LINE_LENGTH].restart();
-        diag2_backward[diag2_base % LINE_LENGTH].restart();
-        diag2_base = if diag2_base == LINE_LENGTH - 1 { 0 } else { diag2_base + 1 };
+    for (variant, t) in template.variants().iter().enumerate() {
+        for y in 0..(grid.height + 1).saturating_sub(t.height) {
+            for x in 0..(grid.width + 1).saturating_sub(t.width) {
+                let position = (x as isize, y as isize);
+
+                if t.matches_at(grid, position) {
+                    matches.push(TemplateMatch { position, variant });
+                }
+            }
+        }
+    }
+
+    return matches;
+}
+
+pub fn part_2(data: File) -> usize {
+    return match_template(&read_grid(data), &Template::parse("M.S/.A./M.S")).len();
+}
+
+pub fn write_template_report(data: File, template: &Template, out: &mut impl Write) -> io::Result<()> {
+    let variants = template.variants();
+    let matches = match_template(&read_grid(data), template);
+
+    for m in matches.iter() {
+        writeln!(out, "{} at {:?}", variants[m.variant], m.position)?;
     }
 
-    return total;
-}
\ No newline at end of file
+    return writeln!(out, "total {}", matches.len());
+}
@@ -1,82 +1,270 @@
-use std::collections::{HashSet};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
 use std::str::FromStr;
 use itertools::Itertools;
+use petgraph::algo::{tarjan_scc, toposort};
+use petgraph::graphmap::DiGraphMap;
 
-pub fn part_1(data: File) -> usize {
-    let mut total = 0;
+// The ordering rules as precedence graph, an edge X -> Y for every rule X|Y.
+pub struct Rules {
+    pub graph: DiGraphMap<u32, ()>,
+}
 
-    let mut lines = BufReader::new(data).lines().flatten();
+#[derive(Debug, Clone)]
+pub struct CyclicRulesError {
+    // the pages of a cycle of rules, where every page has to precede the next and the last the first
+    pub cycle: Vec<u32>,
+}
 
-    let mut forbidden = HashSet::new();
+impl Display for CyclicRulesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "rules are cyclic: {}|{}", self.cycle.iter().join("|"), self.cycle[0]);
+    }
+}
 
-    while let Some(line) = lines.next() {
-        if line.is_empty() { break; }
-        let (x, y) = line.as_str()
-            .split("|").map(|val| u32::from_str(val).unwrap())
-            .next_tuple().unwrap();
+impl Error for CyclicRulesError {}
 
-        forbidden.insert((y, x));
+impl Rules {
+    pub fn is_ordered(&self, update: &[u32]) -> bool {
+        return (0..update.len()).all(|i| (i + 1..update.len()).all(|j| !self.graph.contains_edge(update[j], update[i])));
     }
 
-    for line in lines {
-        let xs = line
-            .split(",")
-            .map(|val| u32::from_str(val).unwrap())
+    // Returns the rules that only involve pages of the update.
+    pub fn restricted(&self, update: &[u32]) -> DiGraphMap<u32, ()> {
+        let mut graph = DiGraphMap::new();
+
+        for &x in update {
+            graph.add_node(x);
+        }
+
+        for &x in update {
+            for y in self.graph.neighbors(x) {
+                if graph.contains_node(y) {
+                    graph.add_edge(x, y, ());
+                }
+            }
+        }
+
+        return graph;
+    }
+
+    // Sorts the update topologically by the rules restricted to its pages, since the full rule
+    // set is allowed to be cyclic as long as every single update is not.
+    pub fn sort(&self, update: &[u32]) -> Result<Vec<u32>, CyclicRulesError> {
+        let graph = self.restricted(update);
+
+        return toposort(&graph, None).map_err(|_| CyclicRulesError { cycle: find_cycle(&graph) });
+    }
+
+    // Lists the violated rules, and corrects the update by moving as few pages as possible.
+    pub fn analyze(&self, update: &[u32]) -> Result<UpdateAnalysis, CyclicRulesError> {
+        let n = update.len();
+        let mut graph = self.restricted(update);
+        toposort(&graph, None).map_err(|_| CyclicRulesError { cycle: find_cycle(&graph) })?;
+
+        let violations = (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .filter(|&(i, j)| self.graph.contains_edge(update[j], update[i]))
+            .map(|(i, j)| Violation { rule: (update[j], update[i]), positions: (j, i) })
             .collect_vec();
 
-        let med = xs[xs.len() / 2];
+        // inverted[i] contains the later positions j whose page has to precede the page at i, possibly
+        // through pages in between, since every such pair needs one of its pages to be moved
+        let index = update.iter().enumerate().map(|(i, &x)| (x, i)).collect::<HashMap<_, _>>();
+        let inverted = (0..n).map(|i| {
+            let mut reachable = HashSet::new();
+            let mut stack = vec![update[i]];
+
+            while let Some(x) = stack.pop() {
+                for y in graph.neighbors_directed(x, petgraph::Direction::Incoming) {
+                    if reachable.insert(y) { stack.push(y); }
+                }
+            }
+
+            return reachable.into_iter().map(|x| index[&x]).filter(|&j| j > i).collect_vec();
+        }).collect_vec();
 
-        if (0..xs.len()).all(|i| (i+1..xs.len()).all(|j| !forbidden.contains(&(xs[i], xs[j])))) {
-            total += med as usize;
+        let kept = largest_consistent_subsequence(&inverted);
+
+        // the kept pages stay in their relative order, the others are inserted wherever the rules allow
+        for (&i, &j) in kept.iter().tuple_windows() {
+            graph.add_edge(update[i], update[j], ());
         }
+
+        return Ok(UpdateAnalysis {
+            violations,
+            moved: (0..n).filter(|i| !kept.contains(i)).map(|i| update[i]).collect(),
+            corrected: toposort(&graph, None).unwrap(),
+        });
     }
+}
 
-    return total;
+#[derive(Debug, Clone)]
+pub struct Violation {
+    // the rule X|Y, where Y was printed before X
+    pub rule: (u32, u32),
+    // the positions of X and Y within the update
+    pub positions: (usize, usize),
 }
 
-pub fn part_2(data: File) -> usize {
-    let mut total = 0;
+#[derive(Debug, Clone)]
+pub struct UpdateAnalysis {
+    pub violations: Vec<Violation>,
+    // the pages to move, as few as possible
+    pub moved: Vec<u32>,
+    pub corrected: Vec<u32>,
+}
 
-    let mut lines = BufReader::new(data).lines().flatten();
+// Returns the largest set of positions without any inverted pair among them. The inverted pairs
+// form a partial order on the positions, hence this is its largest antichain, which by Dilworth's
+// and König's theorem is derived from a maximum matching between the positions and their inversions.
+fn largest_consistent_subsequence(inverted: &[Vec<usize>]) -> Vec<usize> {
+    let n = inverted.len();
+    let mut matched_left = vec![None; n];
+    let mut matched_right = vec![None; n];
+
+    fn augment(
+        i: usize, inverted: &[Vec<usize>], visited: &mut [bool],
+        matched_left: &mut [Option<usize>], matched_right: &mut [Option<usize>],
+    ) -> bool {
+        for &j in inverted[i].iter() {
+            if visited[j] { continue; }
+            visited[j] = true;
+
+            if matched_right[j].is_none_or(|k| augment(k, inverted, visited, matched_left, matched_right)) {
+                matched_left[i] = Some(j);
+                matched_right[j] = Some(i);
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    for i in 0..n {
+        augment(i, inverted, &mut vec![false; n], &mut matched_left, &mut matched_right);
+    }
+
+    // the minimum vertex cover consists of the left vertices not reachable from an unmatched left
+    // vertex by alternating paths, and the right vertices that are reachable
+    let mut reached_left = vec![false; n];
+    let mut reached_right = vec![false; n];
+    let mut stack = (0..n).filter(|&i| matched_left[i].is_none()).collect_vec();
+    stack.iter().for_each(|&i| reached_left[i] = true);
+
+    while let Some(i) = stack.pop() {
+        for &j in inverted[i].iter() {
+            if !reached_right[j] {
+                reached_right[j] = true;
+
+                if let Some(k) = matched_right[j] {
+                    if !reached_left[k] {
+                        reached_left[k] = true;
+                        stack.push(k);
+                    }
+                }
+            }
+        }
+    }
+
+    return (0..n).filter(|&i| reached_left[i] && !reached_right[i]).collect();
+}
 
-    let mut forbidden = HashSet::new();
+fn find_cycle(graph: &DiGraphMap<u32, ()>) -> Vec<u32> {
+    // a cycle is either a strongly connected component of several pages or a page preceding itself
+    let component = tarjan_scc(graph).into_iter()
+        .find(|component| component.len() > 1 || graph.contains_edge(component[0], component[0]))
+        .unwrap();
 
-    while let Some(line) = lines.next() {
+    if component.len() == 1 {
+        return component;
+    }
+
+    // every page of a strongly connected component has a successor within it, hence following
+    // them eventually revisits a page
+    let mut path: Vec<u32> = Vec::new();
+    let mut positions = HashMap::new();
+    let mut curr = component[0];
+
+    while !positions.contains_key(&curr) {
+        positions.insert(curr, path.len());
+        path.push(curr);
+        curr = graph.neighbors(curr).find(|x| component.contains(x)).unwrap();
+    }
+
+    return path.split_off(positions[&curr]);
+}
+
+pub fn read_input(data: File) -> (Rules, Vec<Vec<u32>>) {
+    let mut lines = BufReader::new(data).lines().flatten();
+    let mut graph = DiGraphMap::new();
+
+    for line in lines.by_ref() {
         if line.is_empty() { break; }
         let (x, y) = line.as_str()
             .split("|").map(|val| u32::from_str(val).unwrap())
             .next_tuple().unwrap();
 
-        forbidden.insert((y, x));
+        graph.add_edge(x, y, ());
     }
 
-    for line in lines {
-        let mut xs = line
-            .split(",")
-            .map(|val| u32::from_str(val).unwrap())
-            .collect_vec();
-        
-        let mut valid = true;
-        loop {
-            for i in 0..xs.len() {
-                for j in i + 1..xs.len() {
-                    if forbidden.contains(&(xs[i], xs[j])) { 
-                        xs.swap(i, j);
-                        valid = false;
-                        continue;
-                    }
-                }
-            }
+    let updates = lines
+        .map(|line| line.split(",").map(|val| u32::from_str(val).unwrap()).collect_vec())
+        .collect_vec();
 
-            break;
-        }
+    return (Rules { graph }, updates);
+}
+
+pub fn part_1(data: File) -> usize {
+    let (rules, updates) = read_input(data);
+
+    return updates.iter()
+        .filter(|xs| rules.is_ordered(xs))
+        .map(|xs| xs[xs.len() / 2] as usize)
+        .sum();
+}
+
+pub fn part_2(data: File) -> usize {
+    let (rules, updates) = read_input(data);
+    let mut total = 0;
 
-        if !valid {
-            total += xs[xs.len() / 2] as usize;
+    for xs in updates.iter().filter(|xs| !rules.is_ordered(xs)) {
+        match rules.sort(xs) {
+            Ok(sorted) => { total += sorted[sorted.len() / 2] as usize; },
+            Err(err) => panic!("Cannot order update {xs:?}, {err}"),
         }
     }
 
     return total;
-}
\ No newline at end of file
+}
+
+pub fn write_report(data: File, out: &mut impl Write) -> io::Result<()> {
+    let (rules, updates) = read_input(data);
+
+    for (k, xs) in updates.iter().enumerate() {
+        match rules.analyze(xs) {
+            Ok(analysis) if analysis.violations.is_empty() => writeln!(out, "update {k} ordered")?,
+            Ok(analysis) => {
+                writeln!(
+                    out, "update {k} violates {} rules, fixed by moving {} pages {:?}",
+                    analysis.violations.len(), analysis.moved.len(), analysis.moved,
+                )?;
+
+                for violation in analysis.violations.iter() {
+                    writeln!(
+                        out, "  {}|{} at positions {} and {}",
+                        violation.rule.0, violation.rule.1, violation.positions.0, violation.positions.1,
+                    )?;
+                }
+
+                writeln!(out, "  corrected {}", analysis.corrected.iter().join(","))?;
+            },
+            Err(err) => writeln!(out, "update {k} cannot be ordered, {err}")?,
+        }
+    }
+
+    return Ok(());
+}
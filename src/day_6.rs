@@ -1,10 +1,12 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::sync::mpsc::channel;
+use std::io::{self, BufRead, BufReader, Write};
 use std::thread;
+use log::debug;
+use crate::stats;
 
-#[derive(Debug, Clone, Copy)]
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
     Left = 1<<0, Right = 1<<1, Up = 1<<2, Down = 1<<3
 }
 
@@ -17,6 +19,23 @@ impl Direction {
             &Direction::Down => Direction::Left,
         }
     }
+
+    fn rotated_counter_clockwise(&self) -> Direction {
+        return self.rotated_clockwise().rotated_clockwise().rotated_clockwise();
+    }
+
+    fn index(&self) -> usize {
+        return (*self as u8).trailing_zeros() as usize;
+    }
+
+    pub fn symbol(&self) -> char {
+        return match self {
+            Direction::Left => '<',
+            Direction::Right => '>',
+            Direction::Up => '^',
+            Direction::Down => 'v',
+        };
+    }
 }
 
 enum GuardFieldValue {
@@ -129,24 +148,25 @@ impl<'a> GuardWalk<'a> {
         };
     }
 
-    fn restart(&mut self, pos: (isize, isize), dir: Direction, visited_cache: &[u8]) {
-        self.visited.as_mut_slice().copy_from_slice(visited_cache);
-        self.position = pos;
-        self.direction = dir;
-        self.visited[self.field.to_index(self.position).unwrap()] = 0;
-    }
+    // Renders the visited cells by the axes they were walked along, with the marked obstacles as 'O'.
+    fn to_2d_string(&self, marked: &[(isize, isize)]) -> String {
+        const HORIZONTAL: u8 = Direction::Left as u8 | Direction::Right as u8;
+        const VERTICAL: u8 = Direction::Up as u8 | Direction::Down as u8;
 
-    fn to_2d_string(&self) -> String {
         let mut result = String::new();
 
         for y in 0..self.field.height {
             for x in 0..self.field.width {
-                result.push(match self.field.get((x as isize, y as isize)) {
-                    GuardFieldValue::Empty => if self.visited[self.field.to_index((x as isize, y as isize)).unwrap()] == 0 {
-                        '_'
-                    } else {
-                        'X'
+                let pos = (x as isize, y as isize);
+
+                result.push(match self.field.get(pos) {
+                    GuardFieldValue::Empty => match self.visited[self.field.to_index(pos).unwrap()] {
+                        0 => '_',
+                        v if v & HORIZONTAL == 0 => '|',
+                        v if v & VERTICAL == 0 => '-',
+                        _ => '+',
                     },
+                    GuardFieldValue::Obstacle if marked.contains(&pos) => 'O',
                     GuardFieldValue::Obstacle => '#',
                     GuardFieldValue::OutOfBounds => '-',
                 });
@@ -163,14 +183,101 @@ impl<'a> GuardWalk<'a> {
     }
 }
 
-fn lines_to_field_walk(lines: impl Iterator<Item=String>) -> (GuardField, (isize, isize), Direction) {
+const DIRECTIONS: [Direction; 4] = [Direction::Left, Direction::Right, Direction::Up, Direction::Down];
+
+// For every cell and direction the position the guard reaches in front of the next obstacle,
+// or None if the guard leaves the field, such that walks advance from obstacle to obstacle.
+type Jump = Option<(isize, isize)>;
+
+#[derive(Debug, Clone)]
+struct JumpTable {
+    next: Vec<[Jump; 4]>,
+    // the entries overwritten by placing an obstacle, restored when removing it
+    changes: Vec<(usize, usize, Jump)>,
+    seen: Vec<u32>,
+    generation: u32,
+}
+
+impl JumpTable {
+    fn new(field: &GuardField) -> JumpTable {
+        let mut next = vec![[None; 4]; field.width * field.height];
+
+        for dir in DIRECTIONS {
+            // every line is processed starting at the border the guard is walking towards
+            let (dx, dy) = GuardField::get_next_position((0, 0), dir);
+            let (w, h) = (field.width as isize, field.height as isize);
+            let xs: Vec<isize> = if dx > 0 { (0..w).rev().collect() } else { (0..w).collect() };
+            let ys: Vec<isize> = if dy > 0 { (0..h).rev().collect() } else { (0..h).collect() };
+
+            for &x in xs.iter() {
+                for &y in ys.iter() {
+                    let idx = field.to_index((x, y)).unwrap();
+                    let next_pos = (x + dx, y + dy);
+
+                    next[idx][dir.index()] = match field.get(next_pos) {
+                        GuardFieldValue::Empty => next[field.to_index(next_pos).unwrap()][dir.index()],
+                        GuardFieldValue::Obstacle => Some((x, y)),
+                        GuardFieldValue::OutOfBounds => None,
+                    };
+                }
+            }
+        }
+
+        return JumpTable { next, changes: Vec::new(), seen: vec![0; field.width * field.height * 4], generation: 0 };
+    }
+
+    // Places an obstacle, only updating the cells in line with it up to the previous obstacles.
+    fn place(&mut self, field: &GuardField, obstacle: (isize, isize)) {
+        for dir in DIRECTIONS {
+            let (dx, dy) = GuardField::get_next_position((0, 0), dir);
+            let stop = (obstacle.0 - dx, obstacle.1 - dy);
+            let mut pos = stop;
+
+            while let GuardFieldValue::Empty = field.get(pos) {
+                let idx = field.to_index(pos).unwrap();
+                self.changes.push((idx, dir.index(), self.next[idx][dir.index()]));
+                self.next[idx][dir.index()] = Some(stop);
+
+                pos = (pos.0 - dx, pos.1 - dy);
+            }
+        }
+    }
+
+    fn remove(&mut self) {
+        for (idx, dir, prev) in self.changes.drain(..).rev() {
+            self.next[idx][dir] = prev;
+        }
+    }
+
+    // Returns whether the guard walking from the position in the direction ends up in a loop.
+    fn is_loop(&mut self, field: &GuardField, mut pos: (isize, isize), mut dir: Direction) -> bool {
+        self.generation += 1;
+
+        while let Some(next_pos) = self.next[field.to_index(pos).unwrap()][dir.index()] {
+            pos = next_pos;
+            dir = dir.rotated_clockwise();
+
+            let state = field.to_index(pos).unwrap() * 4 + dir.index();
+            if self.seen[state] == self.generation {
+                return true;
+            }
+            self.seen[state] = self.generation;
+        }
+
+        return false;
+    }
+}
+
+// The start position and direction of a guard.
+type Guard = ((isize, isize), Direction);
+
+// Reads the field and all guards in reading order.
+fn lines_to_field_guards(lines: impl Iterator<Item=String>) -> (GuardField, Vec<Guard>) {
     let mut w = 0;
     let mut h = 0;
 
     let mut field = Vec::new();
-
-    let mut pos = (0, 0);
-    let mut dir = Direction::Down;
+    let mut guards = Vec::new();
 
     for (i, line) in lines.enumerate() {
         if w == 0 {
@@ -184,10 +291,10 @@ fn lines_to_field_walk(lines: impl Iterator<Item=String>) -> (GuardField, (isize
         }));
 
         line.chars().enumerate().for_each(|(j, x)| match x {
-            '^' => { dir = Direction::Up; pos = (j as isize, i as isize); },
-            '<' => { dir = Direction::Left; pos = (j as isize, i as isize); },
-            '>' => { dir = Direction::Right; pos = (j as isize, i as isize); },
-            'v' => { dir = Direction::Down; pos = (j as isize, i as isize); },
+            '^' => { guards.push(((j as isize, i as isize), Direction::Up)); },
+            '<' => { guards.push(((j as isize, i as isize), Direction::Left)); },
+            '>' => { guards.push(((j as isize, i as isize), Direction::Right)); },
+            'v' => { guards.push(((j as isize, i as isize), Direction::Down)); },
             _ => {}
         });
     }
@@ -200,7 +307,14 @@ fn lines_to_field_walk(lines: impl Iterator<Item=String>) -> (GuardField, (isize
 
     return (GuardField::new(
         transposed_field, w, h,
-    ), pos, dir);
+    ), guards);
+}
+
+fn lines_to_field_walk(lines: impl Iterator<Item=String>) -> (GuardField, (isize, isize), Direction) {
+    let (field, guards) = lines_to_field_guards(lines);
+    let (pos, dir) = guards.first().cloned().unwrap_or(((0, 0), Direction::Down));
+
+    return (field, pos, dir);
 }
 
 pub fn part_1(data: File) -> usize {
@@ -208,60 +322,352 @@ pub fn part_1(data: File) -> usize {
 
     let mut walk = GuardWalk::new(&mut field, pos, dir);
     walk.step_until_end();
+    debug!(target: "day6", "Guard left after visiting {} cells", walk.total_visited());
 
     return walk.total_visited();
 }
 
+// A cell on the path of the guard other than the start, with the position and direction of the
+// guard right before reaching it the first time.
+#[derive(Debug, Clone, Copy)]
+struct ObstacleCandidate {
+    obstacle: (isize, isize),
+    position: (isize, isize),
+    direction: Direction,
+}
+
+fn candidate_obstacles(field: &mut GuardField, pos: (isize, isize), dir: Direction) -> Vec<ObstacleCandidate> {
+    let start = pos;
+    let mut walk = GuardWalk::new(field, pos, dir);
+
+    let mut candidates = Vec::new();
+
+    loop {
+        let pos = walk.position;
+        let dir = walk.direction;
+
+        match walk.step() {
+            GuardWalkStepOutcome::Step(true) if walk.position != start => candidates.push(ObstacleCandidate {
+                obstacle: walk.position, position: pos, direction: dir,
+            }),
+            GuardWalkStepOutcome::Step(_) | GuardWalkStepOutcome::Rotate => {},
+            GuardWalkStepOutcome::Loop | GuardWalkStepOutcome::OutOfBounds => { return candidates; },
+        }
+    }
+}
+
+fn find_loops(
+    field: &GuardField,
+    mut jumps: JumpTable,
+    candidates: &[ObstacleCandidate],
+    worker: usize,
+) -> Vec<(isize, isize)> {
+    let mut obstructions = Vec::new();
+
+    for candidate in candidates {
+        jumps.place(field, candidate.obstacle);
+
+        if jumps.is_loop(field, candidate.position, candidate.direction) {
+            obstructions.push(candidate.obstacle);
+        }
+
+        jumps.remove();
+    }
+
+    debug!(target: "day6", "Worker {worker} tested {} obstacles; Found {} loops", candidates.len(), obstructions.len());
+    stats::count("day6", "candidate_obstacles", candidates.len());
+    stats::count("day6", "loops", obstructions.len());
+
+    return obstructions;
+}
+
+// Returns the obstruction positions causing a loop, testing the candidates on the given number of
+// threads, or on the calling thread if at most one thread is requested.
+fn loop_obstructions(field: &mut GuardField, pos: (isize, isize), dir: Direction, threads: usize) -> Vec<(isize, isize)> {
+    let candidates = candidate_obstacles(field, pos, dir);
+    let field = &*field;
+    let jumps = JumpTable::new(field);
+
+    if threads <= 1 {
+        return find_loops(field, jumps, &candidates, 0);
+    }
+
+    let chunk_size = candidates.len().div_ceil(threads).max(1);
+
+    return thread::scope(|scope| {
+        let handles = candidates.chunks(chunk_size).enumerate().map(|(i, chunk)| {
+            let jumps = jumps.clone();
+            return scope.spawn(move || find_loops(field, jumps, chunk, i));
+        }).collect::<Vec<_>>();
+
+        return handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+    });
+}
+
+pub fn part_2_with_threads(data: File, threads: usize) -> usize {
+    let (mut field, pos, dir) = lines_to_field_walk(BufReader::new(data).lines().flatten());
+    return loop_obstructions(&mut field, pos, dir, threads).len();
+}
+
 pub fn part_2(data: File) -> usize {
-    let (field, pos, dir) = lines_to_field_walk(BufReader::new(data).lines().flatten());
+    return part_2_with_threads(data, thread::available_parallelism().map_or(1, |n| n.get()));
+}
+
+#[derive(Debug, Clone)]
+pub struct GuardLoop {
+    pub obstruction: (isize, isize),
+    // the positions and directions of the guard repeated forever, including turns on the spot
+    pub cycle: Vec<((isize, isize), Direction)>,
+    // the number of cells the guard walked from the start before entering the cycle
+    pub entry_step: usize,
+}
+
+// Walks the guard cell by cell with the obstruction placed, returning the loop it ends up in.
+fn trace_loop(field: &mut GuardField, start: (isize, isize), dir: Direction, obstruction: (isize, isize)) -> Option<GuardLoop> {
+    field.set(obstruction, true);
 
-    let (tx, rx) = channel();
+    let mut states = Vec::new();
+    let mut first_seen = HashMap::new();
+    let (mut pos, mut dir, mut steps) = (start, dir, 0);
+
+    let result = loop {
+        if let Some(&(k, entry_step)) = first_seen.get(&(pos, dir.index())) {
+            break Some(GuardLoop { obstruction, cycle: states.split_off(k), entry_step });
+        }
+
+        first_seen.insert((pos, dir.index()), (states.len(), steps));
+        states.push((pos, dir));
+
+        let next_pos = GuardField::get_next_position(pos, dir);
+        match field.get(next_pos) {
+            GuardFieldValue::Empty => { pos = next_pos; steps += 1; },
+            GuardFieldValue::Obstacle => { dir = dir.rotated_clockwise(); },
+            GuardFieldValue::OutOfBounds => { break None; },
+        }
+    };
+
+    field.set(obstruction, false);
+    return result;
+}
+
+pub fn guard_loops(data: File, threads: usize) -> Vec<GuardLoop> {
+    let (mut field, pos, dir) = lines_to_field_walk(BufReader::new(data).lines().flatten());
 
-    let mut handles = Vec::new();
+    let mut obstructions = loop_obstructions(&mut field, pos, dir, threads);
+    obstructions.sort_by_key(|&(x, y)| (y, x));
 
-    const C: usize = 6;
-    for i in 0..C {
-        let mut field = field.clone();
-        let tx = tx.clone();
+    return obstructions.into_iter().map(|obstruction| trace_loop(&mut field, pos, dir, obstruction).unwrap()).collect();
+}
+
+// Lists the loop causing obstructions, or renders the loop of the given obstruction on the map.
+pub fn write_report(data: File, render: Option<(isize, isize)>, out: &mut impl Write) -> io::Result<()> {
+    let (mut field, pos, dir) = lines_to_field_walk(BufReader::new(data).lines().flatten());
+
+    if let Some(obstruction) = render {
+        let Some(guard_loop) = trace_loop(&mut field, pos, dir, obstruction) else {
+            return writeln!(out, "obstruction {obstruction:?} does not cause a loop");
+        };
+
+        field.set(obstruction, true);
+        let mut walk = GuardWalk::new(&mut field, pos, dir);
+        for &(p, d) in guard_loop.cycle.iter() {
+            let idx = walk.field.to_index(p).unwrap();
+            walk.visited[idx] |= d as u8;
+        }
+
+        writeln!(
+            out, "obstruction {obstruction:?} enters a cycle of {} states at step {}",
+            guard_loop.cycle.len(), guard_loop.entry_step,
+        )?;
+        return write!(out, "{}", walk.to_2d_string(&[obstruction]));
+    }
+
+    let mut obstructions = loop_obstructions(&mut field, pos, dir, thread::available_parallelism().map_or(1, |n| n.get()));
+    obstructions.sort_by_key(|&(x, y)| (y, x));
+
+    for obstruction in obstructions {
+        let guard_loop = trace_loop(&mut field, pos, dir, obstruction).unwrap();
+        let (p, d) = guard_loop.cycle[0];
+
+        writeln!(
+            out, "obstruction {obstruction:?} enters a cycle of {} states at step {} at {p:?} {}",
+            guard_loop.cycle.len(), guard_loop.entry_step, d.symbol(),
+        )?;
+    }
+
+    return Ok(());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnRule {
+    Clockwise,
+    CounterClockwise,
+    // starts clockwise and switches the turning direction after every turn
+    Alternating,
+    // the guard stops in front of the first obstacle
+    Stop,
+}
 
-        handles.push(thread::spawn(move || {
-            let mut walk = GuardWalk::new(&mut field, pos, dir);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    // guards walk through each other as if alone
+    Ignore,
+    // guards treat the cells of the other guards as obstacles
+    Block,
+    // guards stop for good once another guard is in their way
+    Halt,
+}
 
-            let mut test_field = walk.field.clone();
-            let mut test_walk = GuardWalk::new(&mut test_field, pos, dir);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GuardStatus {
+    Left { steps: usize },
+    Stopped { steps: usize },
+    Collided { steps: usize },
+    Looping,
+}
 
-            let mut unvisited_counter = 0;
+#[derive(Debug, Clone)]
+pub struct GuardReport {
+    pub start: (isize, isize),
+    pub visited: usize,
+    pub status: GuardStatus,
+}
 
-            let mut total = 0;
-            'outer: loop {
-                let pos = walk.position;
-                let dir = walk.direction;
+#[derive(Debug, Clone)]
+pub struct PatrolReport {
+    // the number of cells visited by any guard
+    pub coverage: usize,
+    pub guards: Vec<GuardReport>,
+}
 
-                match walk.step() {
-                    GuardWalkStepOutcome::Loop => { break 'outer; },
-                    GuardWalkStepOutcome::Rotate => {},
-                    GuardWalkStepOutcome::Step(unvisited) => {
-                        if unvisited && (unvisited_counter % C == i) {
-                            test_walk.field.set(walk.position, true);
-                            test_walk.restart(pos, dir, walk.visited.as_slice());
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PatrolGuard {
+    position: (isize, isize),
+    direction: Direction,
+    clockwise: bool,
+    steps: usize,
+    status: Option<GuardStatus>,
+}
 
-                            if !test_walk.step_until_end() {
-                                total += 1;
-                            }
+// Moves all guards by one step or turn each per tick in reading order, until every guard left,
+// stopped or the state of all guards repeats, in which case the remaining guards loop. Repetitions
+// are found by Brent's cycle detection, such that only a single earlier state is kept, since the
+// joint period of several looping guards is the least common multiple of their own periods.
+fn simulate_patrol(
+    field: &GuardField,
+    guards: &[Guard],
+    turn: TurnRule,
+    collisions: CollisionPolicy,
+    visited: &mut [Vec<bool>],
+) -> Vec<GuardStatus> {
+    let mut guards = guards.iter()
+        .map(|&(position, direction)| PatrolGuard { position, direction, clockwise: true, steps: 0, status: None })
+        .collect::<Vec<_>>();
+
+    for (i, guard) in guards.iter().enumerate() {
+        visited[i][field.to_index(guard.position).unwrap()] = true;
+    }
 
-                            test_walk.field.set(walk.position, false);
-                        }
+    // the steps are left out of the state, as they keep growing within a loop
+    let state = |guards: &[PatrolGuard]| guards.iter().map(|g| PatrolGuard { steps: 0, ..*g }).collect::<Vec<_>>();
+    let mut saved = state(&guards);
+    let mut power = 1;
+    let mut length = 0;
+
+    while guards.iter().any(|guard| guard.status.is_none()) {
+        for i in 0..guards.len() {
+            if guards[i].status.is_some() { continue; }
+
+            let next_pos = GuardField::get_next_position(guards[i].position, guards[i].direction);
+            let occupied = guards.iter().enumerate().any(|(j, other)| {
+                j != i && other.position == next_pos && !matches!(other.status, Some(GuardStatus::Left { .. }))
+            });
+
+            let guard = &mut guards[i];
+            let blocked = match field.get(next_pos) {
+                GuardFieldValue::OutOfBounds => {
+                    guard.status = Some(GuardStatus::Left { steps: guard.steps });
+                    continue;
+                },
+                GuardFieldValue::Obstacle => true,
+                GuardFieldValue::Empty => occupied && collisions == CollisionPolicy::Block,
+            };
 
-                        unvisited_counter += 1;
+            if blocked {
+                guard.direction = match (turn, guard.clockwise) {
+                    (TurnRule::Stop, _) => {
+                        guard.status = Some(GuardStatus::Stopped { steps: guard.steps });
+                        continue;
                     },
-                    GuardWalkStepOutcome::OutOfBounds => { break 'outer; }
+                    (TurnRule::Clockwise, _) | (TurnRule::Alternating, true) => guard.direction.rotated_clockwise(),
+                    (TurnRule::CounterClockwise, _) | (TurnRule::Alternating, false) => guard.direction.rotated_counter_clockwise(),
+                };
+
+                if turn == TurnRule::Alternating {
+                    guard.clockwise = !guard.clockwise;
                 }
-            };
+            } else if occupied && collisions == CollisionPolicy::Halt {
+                guard.status = Some(GuardStatus::Collided { steps: guard.steps });
+            } else {
+                guard.position = next_pos;
+                guard.steps += 1;
+                visited[i][field.to_index(next_pos).unwrap()] = true;
+            }
+        }
 
-            tx.send(total).unwrap();
-        }));
+        length += 1;
+        let current = state(&guards);
+
+        if current == saved {
+            guards.iter_mut().filter(|guard| guard.status.is_none()).for_each(|guard| guard.status = Some(GuardStatus::Looping));
+            break;
+        }
+
+        // the saved state is moved ahead at powers of two, such that it eventually lies on the
+        // cycle while the cycle fits within the following power
+        if length == power {
+            saved = current;
+            power *= 2;
+            length = 0;
+        }
     }
 
-    handles.into_iter().for_each(|h| { h.join().unwrap(); });
-    return rx.iter().take(C).sum();
-}
\ No newline at end of file
+    return guards.into_iter().map(|guard| guard.status.unwrap()).collect();
+}
+
+pub fn patrol(data: File, turn: TurnRule, collisions: CollisionPolicy) -> PatrolReport {
+    let (field, guards) = lines_to_field_guards(BufReader::new(data).lines().flatten());
+    let mut coverage = vec![false; field.width * field.height];
+
+    // without collisions every guard is simulated alone, such that the loops of different guards
+    // are detected without waiting for all of them to line up again
+    let groups = if collisions == CollisionPolicy::Ignore {
+        guards.iter().map(|&guard| vec![guard]).collect::<Vec<_>>()
+    } else {
+        vec![guards.clone()]
+    };
+
+    let mut reports = Vec::new();
+
+    for group in groups {
+        let mut visited = vec![vec![false; field.width * field.height]; group.len()];
+        let statuses = simulate_patrol(&field, &group, turn, collisions, &mut visited);
+
+        for (k, &(start, _)) in group.iter().enumerate() {
+            coverage.iter_mut().zip(visited[k].iter()).for_each(|(x, &y)| *x |= y);
+            reports.push(GuardReport { start, visited: visited[k].iter().filter(|&&x| x).count(), status: statuses[k] });
+        }
+    }
+
+    return PatrolReport { coverage: coverage.iter().filter(|&&x| x).count(), guards: reports };
+}
+
+pub fn write_patrol_report(data: File, turn: TurnRule, collisions: CollisionPolicy, out: &mut impl Write) -> io::Result<()> {
+    let report = patrol(data, turn, collisions);
+
+    for (i, guard) in report.guards.iter().enumerate() {
+        writeln!(out, "guard {i} at {:?} visited {} cells, {:?}", guard.start, guard.visited, guard.status)?;
+    }
+
+    return writeln!(out, "coverage {}", report.coverage);
+}
@@ -1,121 +1,567 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
 use std::mem::swap;
+use std::str::FromStr;
+use itertools::Itertools;
+use log::{debug, log_enabled, Level};
+use num_bigint::{BigUint, ParseBigIntError};
+use crate::stats;
+
+// A non-negative integer computed with checked 64 bit arithmetic, which switches to arbitrary
+// precision for the values that do not fit.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Num(Repr);
+
+// Big only ever holds values above u64::MAX, such that the derived comparison and hash agree with
+// the numeric value, hence values are only built through from_big and the conversions below.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Repr {
+    Small(u64),
+    Big(BigUint),
+}
 
-const COLLECT_STATS: bool = false;
+impl Num {
+    // powers with more bits are considered undefined, as their cost is unbounded otherwise
+    const MAX_POW_BITS: u64 = 1 << 16;
 
-pub fn part_1(data: File) -> usize {
-    let mut total = 0;
+    fn from_big(x: BigUint) -> Num {
+        return Num(u64::try_from(&x).map_or(Repr::Big(x), Repr::Small));
+    }
 
-    let mut total_combs = 0;
-    let mut total_redundants = 0;
+    fn to_big(&self) -> BigUint {
+        return match &self.0 {
+            Repr::Small(x) => BigUint::from(*x),
+            Repr::Big(x) => x.clone(),
+        };
+    }
 
-    for line in BufReader::new(data).lines().flatten() {
-        let (y, xs) = line.split_once(':').unwrap();
+    fn small(&self) -> Option<u64> {
+        return match self.0 {
+            Repr::Small(x) => Some(x),
+            Repr::Big(_) => None,
+        };
+    }
 
-        let y: usize = y.parse().unwrap();
+    pub fn is_zero(&self) -> bool {
+        return self.small() == Some(0);
+    }
 
-        let mut xs = xs.split_whitespace().map(|x| x.parse().unwrap());
-        let (x1, xs) = (xs.next().unwrap(), xs.collect::<Vec<usize>>());
+    fn bits(&self) -> u64 {
+        return match &self.0 {
+            Repr::Small(x) => (u64::BITS - x.leading_zeros()) as u64,
+            Repr::Big(x) => x.bits(),
+        };
+    }
 
-        let mut prev_targets = Vec::new();
-        prev_targets.push(y);
+    pub fn add(&self, other: &Num) -> Num {
+        if let (Some(a), Some(b)) = (self.small(), other.small()) {
+            if let Some(x) = a.checked_add(b) { return Num::from(x); }
+        }
 
-        let mut next_targets = Vec::new();
+        return Num::from_big(self.to_big() + other.to_big());
+    }
 
-        for x in xs.into_iter().rev() {
-            for prev in prev_targets.drain(..).filter(|&prev| prev >= x1) {
-                if prev >= x {
-                    next_targets.push(prev - x);
-                }
+    pub fn checked_sub(&self, other: &Num) -> Option<Num> {
+        if self < other {
+            return None;
+        }
 
-                if prev % x == 0 {
-                    next_targets.push(prev / x);
-                }
-            }
+        return match (self.small(), other.small()) {
+            (Some(a), Some(b)) => Some(Num::from(a - b)),
+            _ => Some(Num::from_big(self.to_big() - other.to_big())),
+        };
+    }
 
-            if COLLECT_STATS {
-                total_combs += next_targets.len();
-                let test: HashSet<usize> = HashSet::from_iter(next_targets.iter().cloned());
-                total_redundants += next_targets.len() - test.len();
-            }
+    pub fn mul(&self, other: &Num) -> Num {
+        if let (Some(a), Some(b)) = (self.small(), other.small()) {
+            if let Some(x) = a.checked_mul(b) { return Num::from(x); }
+        }
+
+        return Num::from_big(self.to_big() * other.to_big());
+    }
+
+    // Returns the quotient and remainder, the divisor must not be zero.
+    pub fn div_rem(&self, other: &Num) -> (Num, Num) {
+        return match (self.small(), other.small()) {
+            (Some(a), Some(b)) => (Num::from(a / b), Num::from(a % b)),
+            _ => {
+                let (a, b) = (self.to_big(), other.to_big());
+                (Num::from_big(&a / &b), Num::from_big(a % b))
+            },
+        };
+    }
 
-            swap(&mut prev_targets, &mut next_targets);
+    pub fn checked_pow(&self, exponent: &Num) -> Option<Num> {
+        if let Some(x @ (0 | 1)) = self.small() {
+            return Some(Num::from(if exponent.is_zero() { 1 } else { x }));
         }
 
-        if prev_targets.contains(&x1) {
-            total += y;
+        let exponent = exponent.small()?;
+        if self.bits().saturating_mul(exponent) > Num::MAX_POW_BITS {
+            return None;
         }
+
+        let exponent = exponent as u32;
+        if let Some(x) = self.small() {
+            if let Some(x) = x.checked_pow(exponent) { return Some(Num::from(x)); }
+        }
+
+        return Some(Num::from_big(self.to_big().pow(exponent)));
     }
 
-    // - required 919922 combinations had 89535 redundants with left-to-right with pruning via exceeding y
-    // - required 23296 combinations had 740 redundants with right-to-left with pruning via going below first value (i.e. exceeding y) and
-    //   requiring divisibility for multiplication
+    // Returns the integer n-th root for n > 0, if the value is an exact power.
+    pub fn exact_root(&self, n: &Num) -> Option<Num> {
+        let n = match n.small() {
+            Some(n) if n > 0 => n,
+            _ => { return None; },
+        };
 
-    if COLLECT_STATS {
-        println!("Total Combintations: {total_combs}; Total Redundant: {total_redundants}");
+        let root = Num::from_big(self.to_big().nth_root(u32::try_from(n).unwrap_or(u32::MAX)));
+        return root.checked_pow(&Num::from(n)).filter(|x| x == self).map(|_| root);
     }
+}
 
-    return total;
+impl From<u64> for Num {
+    fn from(x: u64) -> Num {
+        return Num(Repr::Small(x));
+    }
 }
 
-pub fn part_2(data: File) -> usize {
-    let mut total = 0;
+impl FromStr for Num {
+    type Err = ParseBigIntError;
 
-    let mut total_combs = 0;
-    let mut total_redundants = 0;
+    fn from_str(s: &str) -> Result<Num, ParseBigIntError> {
+        return match s.parse::<u64>() {
+            Ok(x) => Ok(Num::from(x)),
+            Err(_) => Ok(Num::from_big(s.parse()?)),
+        };
+    }
+}
 
-    for line in BufReader::new(data).lines().flatten() {
-        let (y, xs) = line.split_once(':').unwrap();
+impl Display for Num {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match &self.0 {
+            Repr::Small(x) => write!(f, "{x}"),
+            Repr::Big(x) => write!(f, "{x}"),
+        };
+    }
+}
 
-        let y: usize = y.parse().unwrap();
+// The left operands for which left op right equals a given result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inverse {
+    None,
+    One(Num),
+    // every left operand, e.g. when multiplying by zero
+    Any,
+}
 
-        let mut xs = xs.split_whitespace().map(|x| x.parse().unwrap());
-        let (x1, xs) = (xs.next().unwrap(), xs.collect::<Vec<usize>>());
+// A binary operator of the calibration equations, which are evaluated left to right. The solver
+// searches from the test value backwards, hence every operator also provides its inverse.
+pub trait Operator: Sync {
+    fn symbol(&self) -> &str;
 
-        let mut prev_targets = Vec::new();
-        prev_targets.push(y);
+    // Returns left op right, or None if undefined.
+    fn apply(&self, left: &Num, right: &Num) -> Option<Num>;
 
-        let mut next_targets = Vec::new();
+    fn invert(&self, result: &Num, right: &Num) -> Inverse;
 
-        for x in xs.into_iter().rev() {
-            for prev in prev_targets.drain(..).filter(|&prev| prev >= x1) {
-                if prev >= x {
-                    next_targets.push(prev - x);
-                }
+    // Whether left op right is never below left for this right operand, such that targets below the
+    // first operand can be pruned.
+    fn non_decreasing(&self, _right: &Num) -> bool {
+        return false;
+    }
+}
+
+pub struct Add;
+pub struct Mul;
+pub struct Sub;
+pub struct Pow;
+
+// Concatenation of the digits of both operands in the given base.
+pub struct Concat {
+    pub base: u64,
+}
+
+impl Operator for Add {
+    fn symbol(&self) -> &str { return "+"; }
+
+    fn apply(&self, left: &Num, right: &Num) -> Option<Num> {
+        return Some(left.add(right));
+    }
+
+    fn invert(&self, result: &Num, right: &Num) -> Inverse {
+        return result.checked_sub(right).map_or(Inverse::None, Inverse::One);
+    }
+
+    fn non_decreasing(&self, _right: &Num) -> bool {
+        return true;
+    }
+}
+
+impl Operator for Mul {
+    fn symbol(&self) -> &str { return "*"; }
+
+    fn apply(&self, left: &Num, right: &Num) -> Option<Num> {
+        return Some(left.mul(right));
+    }
+
+    fn invert(&self, result: &Num, right: &Num) -> Inverse {
+        if right.is_zero() {
+            return if result.is_zero() { Inverse::Any } else { Inverse::None };
+        }
+
+        let (quotient, remainder) = result.div_rem(right);
+        return if remainder.is_zero() { Inverse::One(quotient) } else { Inverse::None };
+    }
+
+    fn non_decreasing(&self, right: &Num) -> bool {
+        return !right.is_zero();
+    }
+}
+
+impl Operator for Sub {
+    fn symbol(&self) -> &str { return "-"; }
+
+    fn apply(&self, left: &Num, right: &Num) -> Option<Num> {
+        return left.checked_sub(right);
+    }
+
+    fn invert(&self, result: &Num, right: &Num) -> Inverse {
+        return Inverse::One(result.add(right));
+    }
+
+    fn non_decreasing(&self, right: &Num) -> bool {
+        return right.is_zero();
+    }
+}
+
+impl Operator for Pow {
+    fn symbol(&self) -> &str { return "^"; }
+
+    fn apply(&self, left: &Num, right: &Num) -> Option<Num> {
+        return left.checked_pow(right);
+    }
+
+    fn invert(&self, result: &Num, right: &Num) -> Inverse {
+        if right.is_zero() {
+            return if *result == Num::from(1) { Inverse::Any } else { Inverse::None };
+        }
+
+        return result.exact_root(right).map_or(Inverse::None, Inverse::One);
+    }
+}
+
+impl Concat {
+    // Returns the power of the base with as many digits as the value has, with zero having one digit.
+    fn shift(&self, value: &Num) -> Num {
+        let base = Num::from(self.base);
+        let mut shift = base.clone();
+
+        while shift <= *value {
+            shift = shift.mul(&base);
+        }
 
-                if prev % x == 0 {
-                    next_targets.push(prev / x);
+        return shift;
+    }
+}
+
+impl Operator for Concat {
+    fn symbol(&self) -> &str { return "||"; }
+
+    fn apply(&self, left: &Num, right: &Num) -> Option<Num> {
+        return Some(left.mul(&self.shift(right)).add(right));
+    }
+
+    fn invert(&self, result: &Num, right: &Num) -> Inverse {
+        let (quotient, remainder) = result.div_rem(&self.shift(right));
+        return if remainder == *right { Inverse::One(quotient) } else { Inverse::None };
+    }
+
+    fn non_decreasing(&self, _right: &Num) -> bool {
+        return true;
+    }
+}
+
+pub const CONCAT: Concat = Concat { base: 10 };
+
+// Parses an operator symbol, where "||k" concatenates in base k.
+pub fn parse_operator(symbol: &str) -> Option<Box<dyn Operator>> {
+    return match symbol {
+        "+" => Some(Box::new(Add)),
+        "*" => Some(Box::new(Mul)),
+        "-" => Some(Box::new(Sub)),
+        "^" => Some(Box::new(Pow)),
+        "||" => Some(Box::new(CONCAT)),
+        _ => {
+            let base = symbol.strip_prefix("||")?.parse().ok().filter(|&base| base >= 2)?;
+            Some(Box::new(Concat { base }))
+        },
+    };
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SearchStats {
+    combinations: usize,
+    redundant: usize,
+}
+
+fn can_prune(xs: &[Num], operators: &[&dyn Operator]) -> bool {
+    return operators.iter().all(|op| xs[1..].iter().all(|x| op.non_decreasing(x)));
+}
+
+// Counts the operator assignments under which the operands evaluate without an undefined operation,
+// which are all solutions once an inverse accepts any value of these operands.
+fn count_defined(xs: &[Num], operators: &[&dyn Operator]) -> Num {
+    let mut values = HashMap::from([(xs[0].clone(), Num::from(1))]);
+
+    for x in xs[1..].iter() {
+        let mut next_values: HashMap<Num, Num> = HashMap::new();
+
+        for (value, count) in values {
+            for next in operators.iter().filter_map(|op| op.apply(&value, x)) {
+                let entry = next_values.entry(next).or_insert(Num::from(0));
+                *entry = entry.add(&count);
+            }
+        }
+
+        values = next_values;
+    }
+
+    return values.values().fold(Num::from(0), |total, count| total.add(count));
+}
+
+// Searches from the test value backwards, inverting the operators from the last operand to the
+// second, such that only the intermediate values consistent with the test value are considered.
+fn solvable(y: &Num, xs: &[Num], operators: &[&dyn Operator], search_stats: &mut SearchStats) -> bool {
+    let x1 = &xs[0];
+    let prune = can_prune(xs, operators);
+
+    let mut prev_targets = vec![y.clone()];
+    let mut next_targets = Vec::new();
+    let mut any = false;
+
+    for (i, x) in xs.iter().enumerate().skip(1).rev() {
+        for prev in prev_targets.drain(..).filter(|prev| !prune || prev >= x1) {
+            for op in operators.iter() {
+                match op.invert(&prev, x) {
+                    Inverse::None => {},
+                    Inverse::One(next) => next_targets.push(next),
+                    Inverse::Any => { any = any || !count_defined(&xs[..i], operators).is_zero(); },
                 }
+            }
+        }
+
+        if stats::enabled() || log_enabled!(target: "day7", Level::Debug) {
+            search_stats.combinations += next_targets.len();
+            let test: HashSet<&Num> = HashSet::from_iter(next_targets.iter());
+            search_stats.redundant += next_targets.len() - test.len();
+        }
+
+        swap(&mut prev_targets, &mut next_targets);
+    }
+
+    return any || prev_targets.contains(x1);
+}
+
+fn read_equation(line: &str) -> (Num, Vec<Num>) {
+    let (y, xs) = line.split_once(':').unwrap();
+    return (y.parse().unwrap(), xs.split_whitespace().map(|x| x.parse().unwrap()).collect());
+}
+
+// Returns the sum of the test values of the equations solvable with the operators.
+pub fn total_calibration(data: File, operators: &[&dyn Operator], name: &str) -> Num {
+    let mut total = Num::from(0);
+    let mut search_stats = SearchStats::default();
+
+    for line in BufReader::new(data).lines().flatten() {
+        let (y, xs) = read_equation(&line);
+
+        if solvable(&y, &xs, operators, &mut search_stats) {
+            total = total.add(&y);
+        }
+    }
+
+    // - part 1 required 919922 combinations had 89535 redundants with left-to-right with pruning via exceeding y
+    // - part 1 required 23296 combinations had 740 redundants with right-to-left with pruning via going below first
+    //   value (i.e. exceeding y) and requiring divisibility for multiplication
+    // - part 2 required 34053963 combintations had 18823897 redundants with left-to-right with pruning via exceeding y
+    // - part 2 required 35117 combintations had 1126 redundants with right-to-left with pruning via going below first
+    //   value (i.e. exceeding y), requiring divisibility for multiplication, requiring digit match for concatenation
 
-                let m = 10usize.pow(x.ilog10() + 1);
-                if prev % m == x {
-                    next_targets.push(prev / m);
+    debug!(target: "day7", "Total Combinations: {}; Total Redundant: {}", search_stats.combinations, search_stats.redundant);
+    stats::count("day7", &format!("{name}_combinations"), search_stats.combinations);
+    stats::count("day7", &format!("{name}_redundant_combinations"), search_stats.redundant);
+
+    return total;
+}
+
+// Counts the operator assignments by the same backwards search as solvable, merging equal
+// intermediate values and summing up the number of assignments leading to them. The count grows
+// exponentially with the number of operands, hence it is a Num as well.
+pub fn count_solutions(y: &Num, xs: &[Num], operators: &[&dyn Operator]) -> Num {
+    let x1 = &xs[0];
+    let prune = can_prune(xs, operators);
+
+    let mut total = Num::from(0);
+    let mut targets = HashMap::from([(y.clone(), Num::from(1))]);
+
+    for (i, x) in xs.iter().enumerate().skip(1).rev() {
+        let mut next_targets: HashMap<Num, Num> = HashMap::new();
+
+        for (prev, count) in targets.into_iter().filter(|(prev, _)| !prune || prev >= x1) {
+            for op in operators.iter() {
+                match op.invert(&prev, x) {
+                    Inverse::None => {},
+                    Inverse::One(next) => {
+                        let entry = next_targets.entry(next).or_insert(Num::from(0));
+                        *entry = entry.add(&count);
+                    },
+                    Inverse::Any => { total = total.add(&count.mul(&count_defined(&xs[..i], operators))); },
                 }
             }
+        }
+
+        targets = next_targets;
+    }
+
+    return total.add(&targets.remove(x1).unwrap_or(Num::from(0)));
+}
+
+// Returns up to limit operator assignments solving the equation, as indices into the operators.
+pub fn witnesses(y: &Num, xs: &[Num], operators: &[&dyn Operator], limit: usize) -> Vec<Vec<usize>> {
+    // completes the assignment of the suffix by every defined assignment of the operands
+    fn forward(
+        value: &Num, xs: &[Num], operators: &[&dyn Operator], limit: usize,
+        assignment: &mut Vec<usize>, suffix: &[usize], result: &mut Vec<Vec<usize>>,
+    ) {
+        let Some((x, rest)) = xs.split_first() else {
+            result.push(assignment.iter().chain(suffix.iter().rev()).cloned().collect());
+            return;
+        };
+
+        for (k, op) in operators.iter().enumerate() {
+            if result.len() >= limit { return; }
+
+            if let Some(next) = op.apply(value, x) {
+                assignment.push(k);
+                forward(&next, rest, operators, limit, assignment, suffix, result);
+                assignment.pop();
+            }
+        }
+    }
+
+    fn backward(
+        target: &Num, xs: &[Num], operators: &[&dyn Operator], prune: bool, limit: usize,
+        assignment: &mut Vec<usize>, result: &mut Vec<Vec<usize>>,
+    ) {
+        let (x, rest) = xs.split_last().unwrap();
 
-            if COLLECT_STATS {
-                total_combs += next_targets.len();
-                let test: HashSet<usize> = HashSet::from_iter(next_targets.iter().cloned());
-                total_redundants += next_targets.len() - test.len();
+        if rest.is_empty() {
+            if target == x {
+                result.push(assignment.iter().rev().cloned().collect());
             }
+            return;
+        }
 
-            swap(&mut prev_targets, &mut next_targets);
+        if prune && *target < rest[0] {
+            return;
         }
 
-        if prev_targets.contains(&x1) {
-            total += y;
+        for (k, op) in operators.iter().enumerate() {
+            if result.len() >= limit { return; }
+
+            assignment.push(k);
+            match op.invert(target, x) {
+                Inverse::None => {},
+                Inverse::One(prev) => backward(&prev, rest, operators, prune, limit, assignment, result),
+                Inverse::Any => forward(&rest[0], &rest[1..], operators, limit, &mut Vec::new(), assignment, result),
+            }
+            assignment.pop();
         }
     }
 
-    // - required 34053963 combintations had 18823897 redundants with left-to-right with pruning via exceeding y
-    // - required 35117 combintations had 1126 redundants with right-to-left with pruning via going below first value (i.e. exceeding y),
-    //   requiring divisibility for multiplication, requiring digit match for concatenation
+    let mut result = Vec::new();
+    backward(y, xs, operators, can_prune(xs, operators), limit, &mut Vec::new(), &mut result);
 
-    if COLLECT_STATS {
-        println!("Total Combintations: {total_combs}; Total Redundant: {total_redundants}");
+    return result;
+}
+
+// Writes the equation with the operators in place, e.g. "81 + 40 * 27".
+pub fn format_expression(xs: &[Num], operators: &[&dyn Operator], assignment: &[usize]) -> String {
+    let mut result = xs[0].to_string();
+
+    for (x, &k) in xs[1..].iter().zip(assignment.iter()) {
+        result.push_str(&format!(" {} {x}", operators[k].symbol()));
     }
 
-    return total;
-}
\ No newline at end of file
+    return result;
+}
+
+#[derive(Debug, Clone)]
+pub struct EquationAnalysis {
+    pub test_value: Num,
+    pub operands: Vec<Num>,
+    pub solutions: Num,
+    pub witnesses: Vec<String>,
+}
+
+impl EquationAnalysis {
+    pub fn solvable(&self) -> bool {
+        return !self.solutions.is_zero();
+    }
+}
+
+pub fn analyze(data: File, operators: &[&dyn Operator], all_witnesses: bool) -> Vec<EquationAnalysis> {
+    return BufReader::new(data).lines().flatten().map(|line| {
+        let (y, xs) = read_equation(&line);
+        let limit = if all_witnesses { usize::MAX } else { 1 };
+
+        return EquationAnalysis {
+            solutions: count_solutions(&y, &xs, operators),
+            witnesses: witnesses(&y, &xs, operators, limit).iter()
+                .map(|assignment| format_expression(&xs, operators, assignment))
+                .collect(),
+            test_value: y,
+            operands: xs,
+        };
+    }).collect();
+}
+
+pub fn write_report(data: File, operators: &[&dyn Operator], all_witnesses: bool, out: &mut impl Write) -> io::Result<()> {
+    let analyses = analyze(data, operators, all_witnesses);
+
+    for analysis in analyses.iter() {
+        if analysis.solvable() {
+            writeln!(
+                out, "{}: {} solved by {} assignments",
+                analysis.test_value, analysis.operands.iter().join(" "), analysis.solutions,
+            )?;
+        } else {
+            writeln!(out, "{}: {} unsolvable", analysis.test_value, analysis.operands.iter().join(" "))?;
+        }
+
+        for witness in analysis.witnesses.iter() {
+            writeln!(out, "  {} = {witness}", analysis.test_value)?;
+        }
+    }
+
+    let solvable = analyses.iter().filter(|analysis| analysis.solvable()).collect_vec();
+
+    return writeln!(
+        out, "{} of {} equations solvable, total {}",
+        solvable.len(),
+        analyses.len(),
+        solvable.iter().fold(Num::from(0), |total, analysis| total.add(&analysis.test_value)),
+    );
+}
+
+pub fn part_1(data: File) -> Num {
+    return total_calibration(data, &[&Add, &Mul], "part_1");
+}
+
+pub fn part_2(data: File) -> Num {
+    return total_calibration(data, &[&Add, &Mul, &CONCAT], "part_2");
+}
@@ -1,83 +1,204 @@
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use itertools::Itertools;
 use crate::common::Matrix;
 
-pub fn part_1(data: File) -> usize {
-    let matrix = Matrix::from_lines(BufReader::new(data).lines().flatten(), |x, _| x);
+// Which points in line with two antennas of the same frequency are antinodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Harmonics {
+    // the points where one antenna is r times as far away as the other, for every ratio r >= 1,
+    // which lie beyond either antenna, and also between them if interior is set
+    Ratios { ratios: Vec<usize>, interior: bool },
+    // every grid point in line with the antennas
+    Line,
+}
+
+pub struct AntennaMap {
+    pub grid: Matrix<char>,
+    pub antennas: BTreeMap<char, Vec<(isize, isize)>>,
+}
 
-    let mut antennas: HashMap<char, Vec<usize>> = HashMap::new();
+pub fn read_map(data: File) -> AntennaMap {
+    let grid = Matrix::from_lines(BufReader::new(data).lines().flatten(), |x, _| x);
+    let mut antennas: BTreeMap<char, Vec<(isize, isize)>> = BTreeMap::new();
 
-    for (i, &x) in matrix.buffer.iter().enumerate() {
+    for (i, &x) in grid.buffer.iter().enumerate() {
         if x != '.' {
-            antennas.entry(x).and_modify(|x| { x.push(i) }).or_insert(vec![i]);
+            antennas.entry(x).or_default().push(grid.index_to_point(i).unwrap());
         }
     }
-    
-    let mut antinodes = HashSet::new();
 
-    for (_, locs) in antennas.iter() {
-        for i in 0..locs.len() {
-            for j in i + 1..locs.len() {
-                let a = matrix.index_to_point(locs[i]).unwrap();
-                let b = matrix.index_to_point(locs[j]).unwrap();
+    return AntennaMap { grid, antennas };
+}
 
-                let d = (b.0 - a.0, b.1 - a.1);
+fn gcd(a: isize, b: isize) -> isize {
+    return if b == 0 { a.abs() } else { gcd(b, a % b) };
+}
 
-                let c1 = (b.0 + d.0, b.1 + d.1);
-                let c2 = (a.0 - d.0, a.1 - d.1);
+// Returns the positions t = num / den of the points a + t (b - a) where one antenna is r times as
+// far away as the other, which lie beyond either antenna, or between them if interior is set.
+fn ratio_positions(r: usize, interior: bool) -> Vec<(isize, isize)> {
+    let r = r as isize;
+    let mut positions = Vec::new();
 
-                if matrix.point_to_index(c1).is_some() {
-                    antinodes.insert(c1);
-                }
+    if interior {
+        positions.extend([(r, r + 1), (1, r + 1)]);
+    }
+
+    if r > 1 {
+        positions.extend([(r, r - 1), (-1, r - 1)]);
+    }
+
+    return positions;
+}
 
-                if matrix.point_to_index(c2).is_some() {
-                    antinodes.insert(c2);
+// Emits the antinodes of the antennas a and b, including the ones outside the grid. The points in
+// line with both antennas are exactly a + k s for the direction s = (b - a) / gcd, since every
+// coarser step would skip the grid points in between.
+fn pair_antinodes(grid: &Matrix<char>, a: (isize, isize), b: (isize, isize), harmonics: &Harmonics, mut emit: impl FnMut((isize, isize))) {
+    let g = gcd(b.0 - a.0, b.1 - a.1);
+    let step = ((b.0 - a.0) / g, (b.1 - a.1) / g);
+
+    match harmonics {
+        Harmonics::Ratios { ratios, interior } => {
+            // a + t (b - a) = a + t g s is a grid point iff t g is an integer
+            for (num, den) in ratios.iter().filter(|&&r| r > 0).flat_map(|&r| ratio_positions(r, *interior)) {
+                if (num * g) % den == 0 {
+                    let k = num * g / den;
+                    emit((a.0 + k * step.0, a.1 + k * step.1));
+                }
+            }
+        },
+        Harmonics::Line => {
+            for dir in [1, -1] {
+                let mut p = a;
+
+                while grid.point_to_index(p).is_some() {
+                    emit(p);
+                    p = (p.0 + dir * step.0, p.1 + dir * step.1);
                 }
             }
+        },
+    }
+}
+
+// Returns the antinodes of the antennas of every frequency within the grid.
+pub fn antinodes(map: &AntennaMap, harmonics: &Harmonics) -> BTreeMap<char, BTreeSet<(isize, isize)>> {
+    let mut result = BTreeMap::new();
+
+    for (&frequency, locs) in map.antennas.iter() {
+        let mut antinodes = BTreeSet::new();
+
+        for (&a, &b) in locs.iter().tuple_combinations() {
+            pair_antinodes(&map.grid, a, b, harmonics, |p| if map.grid.point_to_index(p).is_some() {
+                antinodes.insert(p);
+            });
         }
+
+        result.insert(frequency, antinodes);
     }
 
-    return antinodes.len();
+    return result;
 }
 
-pub fn part_2(data: File) -> usize {
-    let matrix = Matrix::from_lines(BufReader::new(data).lines().flatten(), |x, _| x);
+#[derive(Debug, Clone)]
+pub struct FrequencyAnalysis {
+    pub frequency: char,
+    pub antennas: usize,
+    pub pairs: usize,
+    pub inside: BTreeSet<(isize, isize)>,
+    // the antinodes beyond the edge of the map, which are always empty for lines
+    pub outside: BTreeSet<(isize, isize)>,
+    // the number of antinodes shared with every other frequency that shares any
+    pub overlaps: BTreeMap<char, usize>,
+}
 
-    let mut antennas: HashMap<char, Vec<usize>> = HashMap::new();
+pub fn analyze(map: &AntennaMap, harmonics: &Harmonics) -> Vec<FrequencyAnalysis> {
+    let mut analyses = map.antennas.iter().map(|(&frequency, locs)| {
+        let mut inside = BTreeSet::new();
+        let mut outside = BTreeSet::new();
 
-    for (i, &x) in matrix.buffer.iter().enumerate() {
-        if x != '.' {
-            antennas.entry(x).and_modify(|x| { x.push(i) }).or_insert(vec![i]);
+        for (&a, &b) in locs.iter().tuple_combinations() {
+            pair_antinodes(&map.grid, a, b, harmonics, |p| {
+                if map.grid.point_to_index(p).is_some() { inside.insert(p); } else { outside.insert(p); }
+            });
+        }
+
+        return FrequencyAnalysis {
+            frequency,
+            antennas: locs.len(),
+            pairs: locs.len() * locs.len().saturating_sub(1) / 2,
+            inside,
+            outside,
+            overlaps: BTreeMap::new(),
+        };
+    }).collect_vec();
+
+    for i in 0..analyses.len() {
+        for j in 0..analyses.len() {
+            let shared = analyses[i].inside.intersection(&analyses[j].inside).count();
+
+            if i != j && shared > 0 {
+                let frequency = analyses[j].frequency;
+                analyses[i].overlaps.insert(frequency, shared);
+            }
         }
     }
 
-    let mut antinodes = HashSet::new();
+    return analyses;
+}
 
-    for (_, locs) in antennas.iter() {
-        for i in 0..locs.len() {
-            for j in i + 1..locs.len() {
-                let a = matrix.index_to_point(locs[i]).unwrap();
-                let b = matrix.index_to_point(locs[j]).unwrap();
+// Draws the antinodes as '#' onto the map, keeping the antennas visible like the puzzle does.
+pub fn overlay(map: &AntennaMap, antinodes: &BTreeMap<char, BTreeSet<(isize, isize)>>) -> String {
+    let mut grid = map.grid.clone();
 
-                let d = (b.0 - a.0, b.1 - a.1);
+    for &p in antinodes.values().flatten() {
+        let i = grid.point_to_index(p).unwrap();
+        if grid.buffer[i] == '.' { grid.buffer[i] = '#'; }
+    }
 
-                let mut cp = a;
-                while let Some(cpi) = matrix.point_to_index(cp) {
-                    antinodes.insert(cpi);
-                    cp.0 += d.0;
-                    cp.1 += d.1;
-                }
+    return grid.display_string();
+}
 
-                let mut cp = (a.0 - d.0, a.1 - d.1);
-                while let Some(cpi) = matrix.point_to_index(cp) {
-                    antinodes.insert(cpi);
-                    cp.0 -= d.0;
-                    cp.1 -= d.1;
-                }
-            }
+pub fn write_report(data: File, harmonics: &Harmonics, render: bool, out: &mut impl Write) -> io::Result<()> {
+    let map = read_map(data);
+    let analyses = analyze(&map, harmonics);
+
+    for analysis in analyses.iter() {
+        writeln!(
+            out, "{}: {} antennas, {} pairs, {} antinodes inside, {} outside",
+            analysis.frequency, analysis.antennas, analysis.pairs, analysis.inside.len(), analysis.outside.len(),
+        )?;
+
+        if !analysis.overlaps.is_empty() {
+            writeln!(
+                out, "  overlaps {}",
+                analysis.overlaps.iter().map(|(frequency, shared)| format!("{frequency}: {shared}")).join(", "),
+            )?;
         }
     }
 
-    return antinodes.len();
-}
\ No newline at end of file
+    let antinodes: BTreeMap<_, _> = analyses.iter().map(|analysis| (analysis.frequency, analysis.inside.clone())).collect();
+    writeln!(out, "{} distinct antinodes", antinodes.values().flatten().collect::<HashSet<_>>().len())?;
+
+    if render {
+        write!(out, "{}", overlay(&map, &antinodes))?;
+    }
+
+    return Ok(());
+}
+
+// Counts the distinct locations with an antinode of any frequency.
+pub fn count_antinodes(data: File, harmonics: &Harmonics) -> usize {
+    let antinodes = antinodes(&read_map(data), harmonics);
+    return antinodes.values().flatten().collect::<HashSet<_>>().len();
+}
+
+pub fn part_1(data: File) -> usize {
+    return count_antinodes(data, &Harmonics::Ratios { ratios: vec![2], interior: false });
+}
+
+pub fn part_2(data: File) -> usize {
+    return count_antinodes(data, &Harmonics::Line);
+}
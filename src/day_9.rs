@@ -1,89 +1,306 @@
-use std::array::from_fn;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::collections::{BTreeSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::collections::{BTreeMap, BTreeSet};
+use itertools::Itertools;
 
-pub fn part_1(data: File) -> usize {
-    let line = BufReader::new(data).lines().flatten().next().unwrap();
-    let mut disk = Vec::with_capacity(line.len() * 9);
+// A maximal run of blocks that belong to the same file or are all free.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent {
+    pub start: usize,
+    pub len: usize,
+    pub file: Option<usize>,
+}
+
+impl Display for Extent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self.file {
+            Some(id) => write!(f, "{id}x{}", self.len),
+            None => write!(f, ".x{}", self.len),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FragmentationStats {
+    // the files with any block at a different position than in the disk map read
+    pub files_moved: usize,
+    // the files whose blocks are not contiguous
+    pub files_split: usize,
+    pub largest_free_extent: usize,
+    // the number of free extents of every size, including the free space at the end of the disk
+    pub free_extents: BTreeMap<usize, usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    // moves single blocks from the end into the first free block
+    Blocks,
+    // moves whole files, from the last to the first, into the leftmost free extent that fits
+    FirstFit,
+    // into the smallest free extent that fits, the leftmost among equal sizes
+    BestFit,
+    // into the largest free extent, the leftmost among equal sizes
+    WorstFit,
+    // repeats first fit passes until no file can move anymore
+    MultiPass,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 5] = [Strategy::Blocks, Strategy::FirstFit, Strategy::BestFit, Strategy::WorstFit, Strategy::MultiPass];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Strategy::Blocks => "blocks",
+            Strategy::FirstFit => "first-fit",
+            Strategy::BestFit => "best-fit",
+            Strategy::WorstFit => "worst-fit",
+            Strategy::MultiPass => "multi-pass",
+        };
+    }
+
+    pub fn parse(name: &str) -> Option<Strategy> {
+        return Strategy::ALL.into_iter().find(|strategy| strategy.name() == name);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Fit {
+    First,
+    Best,
+    Worst,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Compaction {
+    // the number of single blocks or whole file extents moved
+    pub moves: usize,
+    pub blocks_moved: usize,
+}
 
-    for (i, c) in line.chars().enumerate() {
-        for _ in 0..c.to_digit(10).unwrap() {
-            disk.push(if i % 2 == 0 { 1 + i / 2 } else { 0 });
+// The blocks of a disk, each belonging to a file or being free, where the files are identified by
+// their order within the disk map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskMap {
+    pub blocks: Vec<Option<usize>>,
+    // the first block of every file as read, by which moves are detected
+    original_starts: Vec<usize>,
+}
+
+impl DiskMap {
+    // Parses the dense format, alternating between the sizes of a file and the free space after it.
+    pub fn parse(line: &str) -> DiskMap {
+        let mut blocks = Vec::with_capacity(line.len() * 9);
+        let mut original_starts = Vec::with_capacity(line.len() / 2 + 1);
+
+        for (i, c) in line.chars().enumerate() {
+            if i % 2 == 0 {
+                original_starts.push(blocks.len());
+            }
+
+            for _ in 0..c.to_digit(10).unwrap() {
+                blocks.push(if i % 2 == 0 { Some(i / 2) } else { None });
+            }
         }
+
+        return DiskMap { blocks, original_starts };
     }
 
-    let mut i = 0;
-    for j in (0..disk.len()).rev() {
-        if disk[j] == 0 {
-            continue;
+    pub fn read(data: File) -> DiskMap {
+        return DiskMap::parse(&BufReader::new(data).lines().flatten().next().unwrap());
+    }
+
+    pub fn extents(&self) -> Vec<Extent> {
+        let mut extents: Vec<Extent> = Vec::new();
+
+        for (i, &file) in self.blocks.iter().enumerate() {
+            match extents.last_mut() {
+                Some(extent) if extent.file == file => extent.len += 1,
+                _ => extents.push(Extent { start: i, len: 1, file }),
+            }
         }
 
-        while i < j && disk[i] != 0 {
+        return extents;
+    }
+
+    pub fn checksum(&self) -> usize {
+        return self.blocks.iter().enumerate().map(|(i, x)| i * x.unwrap_or(0)).sum();
+    }
+
+    pub fn compact(&mut self, strategy: Strategy) -> Compaction {
+        return match strategy {
+            Strategy::Blocks => self.compact_blocks(),
+            Strategy::FirstFit => self.compact_files(Fit::First),
+            Strategy::BestFit => self.compact_files(Fit::Best),
+            Strategy::WorstFit => self.compact_files(Fit::Worst),
+            Strategy::MultiPass => {
+                let mut total = Compaction::default();
+
+                // every move is to the left, hence this ends eventually
+                loop {
+                    let compaction = self.compact_files(Fit::First);
+                    if compaction.moves == 0 { break; }
+
+                    total.moves += compaction.moves;
+                    total.blocks_moved += compaction.blocks_moved;
+                }
+
+                total
+            },
+        };
+    }
+
+    // Moves the last file block into the first free block until no free block precedes a file block.
+    fn compact_blocks(&mut self) -> Compaction {
+        let mut compaction = Compaction::default();
+        let mut i = 0;
+
+        for j in (0..self.blocks.len()).rev() {
+            if self.blocks[j].is_none() {
+                continue;
+            }
+
+            while i < j && self.blocks[i].is_some() {
+                i += 1;
+            }
+
+            if i >= j {
+                break;
+            }
+
+            self.blocks.swap(i, j);
+            compaction.moves += 1;
+            compaction.blocks_moved += 1;
             i += 1;
         }
 
-        if i >= j {
-            break;
+        return compaction;
+    }
+
+    // Moves every file extent, from the last to the first, as a whole to a free extent before it
+    // that fits, chosen by the fit.
+    fn compact_files(&mut self, fit: Fit) -> Compaction {
+        let mut compaction = Compaction::default();
+        let extents = self.extents();
+
+        // the starts of the free extents by their size
+        let mut free: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for extent in extents.iter().filter(|extent| extent.file.is_none()) {
+            free.entry(extent.len).or_default().insert(extent.start);
         }
 
-        disk.swap(i, j);
+        // the space vacated by a file is after every remaining file, hence never needs to be reused
+        for extent in extents.iter().rev().filter(|extent| extent.file.is_some()) {
+            let mut candidates = free.range(extent.len..)
+                .filter_map(|(&size, starts)| starts.first().map(|&start| (size, start)))
+                .filter(|&(_, start)| start < extent.start);
 
-        i += 1;
-    }
+            let target = match fit {
+                Fit::First => candidates.min_by_key(|&(_, start)| start),
+                Fit::Best => candidates.next(),
+                Fit::Worst => candidates.next_back(),
+            };
 
-    return disk.iter()
-        .take_while(|&&x| x != 0)
-        .enumerate()
-        .map(|(i, &x)| i * (x - 1))
-        .sum::<usize>();
-}
+            if let Some((size, start)) = target {
+                free.get_mut(&size).unwrap().remove(&start);
 
-pub fn part_2(data: File) -> usize {
-    let line = BufReader::new(data).lines().flatten().next().unwrap();
-
-    let mut free: [_; 9] = from_fn(|_| BTreeSet::<usize>::new());
-    let mut files = Vec::with_capacity(line.len() / 2);
-
-    let mut disk_len = 0;
-    for (i, c) in line.chars().enumerate() {
-        let size = c.to_digit(10).unwrap() as usize;
-        
-        if size > 0 {
-            if i % 2 != 0 {
-                free[size - 1].insert(disk_len);
-            } else {
-                files.push((disk_len, size));
+                if size > extent.len {
+                    free.entry(size - extent.len).or_default().insert(start + extent.len);
+                }
+
+                for k in 0..extent.len {
+                    self.blocks.swap(start + k, extent.start + k);
+                }
+
+                compaction.moves += 1;
+                compaction.blocks_moved += extent.len;
             }
         }
 
-        disk_len += size;
+        return compaction;
     }
 
-    let mut checksum = 0;
-    
-    for (id, (entry_j, entry_size)) in files.into_iter().enumerate().rev() {
-        let free_entry = ((entry_size - 1)..9)
-            .map(|s| free[s].first().cloned().map(|i| (s + 1, i)))
-            .flatten()
-            .min_by_key(|(_, i)| *i);
+    // Writes every block as the last digit of its file, or '.' if free, as the puzzle illustrates it.
+    pub fn to_block_string(&self) -> String {
+        return self.blocks.iter().map(|x| match x {
+            Some(id) => char::from_digit((id % 10) as u32, 10).unwrap(),
+            None => '.',
+        }).collect();
+    }
 
-        if let Some((free_size, free_i)) = free_entry {
-            if free_i < entry_j {
-                free[free_size - 1].remove(&free_i);
+    // Writes the extents as the file or '.' and their length, e.g. "0x2 .x3 9x2".
+    pub fn to_run_length_string(&self) -> String {
+        return self.extents().iter().join(" ");
+    }
 
-                checksum += id * (free_i * entry_size + (entry_size * (entry_size - 1)) / 2);
+    pub fn stats(&self) -> FragmentationStats {
+        let extents = self.extents();
+        let mut file_extents = vec![0; self.original_starts.len()];
+        let mut starts = vec![None; self.original_starts.len()];
+        let mut free_extents = BTreeMap::new();
 
-                if free_size > entry_size {
-                    free[free_size - entry_size - 1].insert(free_i + entry_size);
-                }
-            } else {
-                checksum += id * (entry_j * entry_size + (entry_size * (entry_size - 1)) / 2);
+        for extent in extents.iter() {
+            match extent.file {
+                Some(id) => {
+                    file_extents[id] += 1;
+                    starts[id].get_or_insert(extent.start);
+                },
+                None => *free_extents.entry(extent.len).or_insert(0) += 1,
             }
-        } else {
-            checksum += id * (entry_j * entry_size + (entry_size * (entry_size - 1)) / 2);
         }
+
+        // a file that kept its first block kept all of them, unless it was split, while an empty
+        // file has no blocks to move
+        return FragmentationStats {
+            files_moved: (0..starts.len())
+                .filter(|&id| starts[id].is_some_and(|start| start != self.original_starts[id] || file_extents[id] > 1))
+                .count(),
+            files_split: file_extents.iter().filter(|&&count| count > 1).count(),
+            largest_free_extent: free_extents.keys().last().cloned().unwrap_or(0),
+            free_extents,
+        };
     }
-    
-    return checksum;
+}
+
+// Compacts the disk by every strategy, writing the layouts only if requested since they are as
+// long as the disk.
+pub fn write_report(data: File, strategies: &[Strategy], layout: bool, out: &mut impl Write) -> io::Result<()> {
+    let original = DiskMap::read(data);
+
+    for &strategy in strategies {
+        let mut disk = original.clone();
+        let compaction = disk.compact(strategy);
+        let stats = disk.stats();
+
+        writeln!(
+            out, "{}: checksum {}, {} moves, {} blocks moved",
+            strategy.name(), disk.checksum(), compaction.moves, compaction.blocks_moved,
+        )?;
+
+        if layout {
+            writeln!(out, "  {}", disk.to_block_string())?;
+            writeln!(out, "  {}", disk.to_run_length_string())?;
+        }
+
+        writeln!(out, "  {} files moved, {} files split", stats.files_moved, stats.files_split)?;
+        writeln!(out, "  largest free extent {}", stats.largest_free_extent)?;
+        writeln!(
+            out, "  free extents {}",
+            stats.free_extents.iter().map(|(size, count)| format!("{size}: {count}")).join(", "),
+        )?;
+    }
+
+    return Ok(());
+}
+
+pub fn part_1(data: File) -> usize {
+    let mut disk = DiskMap::read(data);
+    disk.compact(Strategy::Blocks);
+    return disk.checksum();
+}
+
+pub fn part_2(data: File) -> usize {
+    let mut disk = DiskMap::read(data);
+    disk.compact(Strategy::FirstFit);
+    return disk.checksum();
 }
@@ -0,0 +1,96 @@
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::ops::Range;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use crate::generator::{generate, input_file};
+use crate::reference;
+
+pub struct Check {
+    pub day: usize,
+    pub part: usize,
+    pub size: usize,
+    fast: fn(File) -> String,
+    reference: fn(File) -> String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Solved(String),
+    Panicked,
+    TimedOut(Duration),
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Outcome::Solved(result) => write!(f, "{result}"),
+            Outcome::Panicked => write!(f, "panicked"),
+            Outcome::TimedOut(timeout) => write!(f, "timed out after {:.2}s", timeout.as_secs_f32()),
+        };
+    }
+}
+
+// An input which the solvers disagree on, or which at least one of them failed to solve.
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub day: usize,
+    pub part: usize,
+    pub seed: u64,
+    pub input: String,
+    pub fast: Outcome,
+    pub reference: Outcome,
+}
+
+pub fn checks() -> Vec<Check> {
+    return vec![
+        Check { day: 6, part: 2, size: 11, fast: |f| crate::day_6::part_2(f).to_string(), reference: |f| reference::day_6_part_2(f).to_string() },
+        Check { day: 7, part: 1, size: 30, fast: |f| crate::day_7::part_1(f).to_string(), reference: |f| reference::day_7_part_1(f).to_string() },
+        Check { day: 7, part: 2, size: 30, fast: |f| crate::day_7::part_2(f).to_string(), reference: |f| reference::day_7_part_2(f).to_string() },
+        Check { day: 9, part: 1, size: 30, fast: |f| crate::day_9::part_1(f).to_string(), reference: |f| reference::day_9_part_1(f).to_string() },
+        Check { day: 9, part: 2, size: 30, fast: |f| crate::day_9::part_2(f).to_string(), reference: |f| reference::day_9_part_2(f).to_string() },
+        Check { day: 12, part: 1, size: 12, fast: |f| crate::day_12::part_1(f).to_string(), reference: |f| reference::day_12_part_1(f).to_string() },
+        Check { day: 12, part: 2, size: 12, fast: |f| crate::day_12::part_2(f).to_string(), reference: |f| reference::day_12_part_2(f).to_string() },
+        Check { day: 17, part: 2, size: 0, fast: |f| crate::day_17::part_2(f).to_string(), reference: |f| reference::day_17_part_2(f).to_string() },
+        Check { day: 21, part: 1, size: 5, fast: |f| crate::day_21::part_1(f).to_string(), reference: |f| reference::day_21_part_1(f).to_string() },
+    ];
+}
+
+// Runs the solver on a separate thread, such that panics and runaway searches are reported as
+// outcomes instead of bringing down the whole run. A timed out thread cannot be stopped and keeps
+// running in the background, hence a check ends at its first failure.
+fn run_solver(solver: fn(File) -> String, input: &str, timeout: Duration) -> Outcome {
+    let (tx, rx) = channel();
+    let data = input_file(input);
+
+    thread::spawn(move || {
+        let result = solver(data);
+        let _ = tx.send(result);
+    });
+
+    return match rx.recv_timeout(timeout) {
+        Ok(result) => Outcome::Solved(result),
+        Err(RecvTimeoutError::Timeout) => Outcome::TimedOut(timeout),
+        Err(RecvTimeoutError::Disconnected) => Outcome::Panicked,
+    };
+}
+
+impl Check {
+    // Returns the first seed whose generated input is solved differently by the fast and reference
+    // solver, or which either of them fails to solve.
+    pub fn run(&self, seeds: Range<u64>, timeout: Duration) -> Option<Mismatch> {
+        for seed in seeds {
+            let input = generate(self.day, seed, self.size);
+
+            let fast = run_solver(self.fast, &input, timeout);
+            let reference = run_solver(self.reference, &input, timeout);
+
+            if !matches!((&fast, &reference), (Outcome::Solved(a), Outcome::Solved(b)) if a == b) {
+                return Some(Mismatch { day: self.day, part: self.part, seed, input, fast, reference });
+            }
+        }
+
+        return None;
+    }
+}
@@ -0,0 +1,655 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use itertools::Itertools;
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use seq_macro::seq;
+use crate::day_17::eval_program;
+use crate::reference::day_6_is_loop;
+
+// ChaCha8 has a value-stable output stream, so inputs are reproducible from the seed
+// independently of the platform and the rand version.
+pub type GeneratorRng = ChaCha8Rng;
+
+pub fn rng(seed: u64) -> GeneratorRng {
+    return ChaCha8Rng::seed_from_u64(seed);
+}
+
+pub fn generate(day: usize, seed: u64, size: usize) -> String {
+    let mut rng = rng(seed);
+
+    seq!(N in 1..=25 {
+        return match day {
+            #( N => day_~N(&mut rng, size), )*
+            _ => panic!("Invalid day {day}"),
+        };
+    });
+}
+
+// Writes the input to an anonymous temporary file, such that it can be passed to the solvers.
+pub fn input_file(input: &str) -> File {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let path = std::env::temp_dir().join(format!(
+        "aoc-2024-{}-{}.txt", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    File::create(&path).unwrap().write_all(input.as_bytes()).unwrap();
+    let file = File::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    return file;
+}
+
+fn grid_to_string(grid: &[char], width: usize) -> String {
+    return grid.chunks(width).map(|row| row.iter().collect::<String>()).join("\n");
+}
+
+fn random_name(rng: &mut GeneratorRng, alphabet: &[u8], len: usize, used: &mut HashSet<String>) -> String {
+    loop {
+        let name = (0..len).map(|_| *alphabet.choose(rng).unwrap() as char).collect::<String>();
+
+        if used.insert(name.clone()) {
+            return name;
+        }
+    }
+}
+
+// Carves a perfect maze on a (2w + 1) x (2h + 1) grid using a randomized depth-first search starting
+// at cell (0, 0). Returns the wall grid and the longest path from the starting cell in grid coordinates.
+fn carve_maze(rng: &mut GeneratorRng, w: usize, h: usize) -> (Vec<bool>, Vec<(usize, usize)>) {
+    let width = 2 * w + 1;
+    let mut walls = vec![true; width * (2 * h + 1)];
+    let mut visited = vec![false; w * h];
+
+    let mut stack = vec![(0, 0)];
+    let mut longest = vec![(0, 0)];
+    visited[0] = true;
+    walls[width + 1] = false;
+
+    while let Some(&(x, y)) = stack.last() {
+        let mut next = [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter()
+            .map(|d| (x as isize + d.0, y as isize + d.1))
+            .filter(|&(nx, ny)| nx >= 0 && ny >= 0 && (nx as usize) < w && (ny as usize) < h)
+            .map(|(nx, ny)| (nx as usize, ny as usize))
+            .filter(|&(nx, ny)| !visited[ny * w + nx])
+            .collect_vec();
+
+        next.shuffle(rng);
+
+        if let Some(&(nx, ny)) = next.first() {
+            visited[ny * w + nx] = true;
+            walls[(2 * ny + 1) * width + 2 * nx + 1] = false;
+            walls[(y + ny + 1) * width + x + nx + 1] = false;
+            stack.push((nx, ny));
+
+            if stack.len() > longest.len() {
+                longest = stack.clone();
+            }
+        } else {
+            stack.pop();
+        }
+    }
+
+    let mut path = vec![(1, 1)];
+    for (&(x0, y0), &(x1, y1)) in longest.iter().tuple_windows() {
+        path.push((x0 + x1 + 1, y0 + y1 + 1));
+        path.push((2 * x1 + 1, 2 * y1 + 1));
+    }
+
+    return (walls, path);
+}
+
+// Two columns of location ids, where some of the right ids are reused from the left column.
+pub fn day_1(rng: &mut GeneratorRng, size: usize) -> String {
+    let left = (0..size).map(|_| rng.gen_range(10000..100000usize)).collect_vec();
+
+    return left.iter().map(|&l| {
+        let r = if rng.gen_bool(0.3) { *left.choose(rng).unwrap() } else { rng.gen_range(10000..100000) };
+        format!("{l}   {r}")
+    }).join("\n");
+}
+
+// Monotone reports with valid steps, some of which get one or two levels corrupted.
+pub fn day_2(rng: &mut GeneratorRng, size: usize) -> String {
+    return (0..size).map(|_| {
+        let len = rng.gen_range(5..=8);
+        let upwards = rng.gen_bool(0.5);
+
+        let mut levels = vec![rng.gen_range(30..70usize)];
+        for _ in 1..len {
+            let step = rng.gen_range(1..=3);
+            let prev = *levels.last().unwrap();
+            levels.push(if upwards { prev + step } else { prev - step });
+        }
+
+        for _ in 0..rng.gen_range(0..=2) {
+            let i = rng.gen_range(0..len);
+            levels[i] = rng.gen_range(1..100);
+        }
+
+        levels.into_iter().join(" ")
+    }).join("\n");
+}
+
+// Corrupted memory containing valid instructions, near misses and noise.
+pub fn day_3(rng: &mut GeneratorRng, size: usize) -> String {
+    const NOISE: &[u8] = b"mul(),don't0123456789 []{}<>@#%*?+-'select_from_whatwhowherehow";
+
+    let mut result = String::new();
+
+    for i in 0..size {
+        match rng.gen_range(0..10) {
+            0..=3 => result.push_str(&format!("mul({},{})", rng.gen_range(1..1000), rng.gen_range(1..1000))),
+            4 => result.push_str("do()"),
+            5 => result.push_str("don't()"),
+            6 => result.push_str(&match rng.gen_range(0..4) {
+                0 => format!("mul( {},{})", rng.gen_range(1..1000), rng.gen_range(1..1000)),
+                1 => format!("mul({}*{})", rng.gen_range(1..1000), rng.gen_range(1..1000)),
+                2 => format!("mul[{},{}]", rng.gen_range(1..1000), rng.gen_range(1..1000)),
+                _ => format!("mul({},{})", rng.gen_range(1000..100000), rng.gen_range(1..1000)),
+            }),
+            _ => (0..rng.gen_range(1..8)).for_each(|_| result.push(*NOISE.choose(rng).unwrap() as char)),
+        }
+
+        if i % 64 == 63 {
+            result.push('\n');
+        }
+    }
+
+    return result;
+}
+
+// A size x size letter grid with planted XMAS words.
+pub fn day_4(rng: &mut GeneratorRng, size: usize) -> String {
+    let mut grid = (0..size * size).map(|_| *[b'X', b'M', b'A', b'S'].choose(rng).unwrap() as char).collect_vec();
+
+    for _ in 0..size {
+        let d: (isize, isize) = (rng.gen_range(-1..=1), rng.gen_range(-1..=1));
+        let p = (rng.gen_range(0..size) as isize, rng.gen_range(0..size) as isize);
+
+        if d == (0, 0) { continue; }
+
+        for (i, c) in ['X', 'M', 'A', 'S'].into_iter().enumerate() {
+            let q = (p.0 + d.0 * i as isize, p.1 + d.1 * i as isize);
+
+            if q.0 >= 0 && q.1 >= 0 && (q.0 as usize) < size && (q.1 as usize) < size {
+                grid[q.1 as usize * size + q.0 as usize] = c;
+            }
+        }
+    }
+
+    return grid_to_string(&grid, size);
+}
+
+// Ordering rules for every pair of pages of a hidden total order, followed by updates.
+pub fn day_5(rng: &mut GeneratorRng, size: usize) -> String {
+    let mut pages = (10..100usize).collect_vec();
+    pages.shuffle(rng);
+    pages.truncate(25);
+
+    let mut lines = Vec::new();
+    for i in 0..pages.len() {
+        for j in i + 1..pages.len() {
+            lines.push(format!("{}|{}", pages[i], pages[j]));
+        }
+    }
+    lines.shuffle(rng);
+    lines.push(String::new());
+
+    for _ in 0..size {
+        let len = 2 * rng.gen_range(1..=pages.len() / 2) + 1;
+        let mut update = pages.choose_multiple(rng, len).cloned().collect_vec();
+
+        if rng.gen_bool(0.5) {
+            update.sort_by_key(|x| pages.iter().position(|y| y == x).unwrap());
+        }
+
+        lines.push(update.into_iter().join(","));
+    }
+
+    return lines.join("\n");
+}
+
+// A guard map with scattered obstacles and a single guard.
+pub fn day_6(rng: &mut GeneratorRng, size: usize) -> String {
+    loop {
+        let mut grid = (0..size * size).map(|_| if rng.gen_bool(0.1) { '#' } else { '.' }).collect_vec();
+
+        let start = rng.gen_range(0..grid.len());
+        grid[start] = *['^', '>', 'v', '<'].choose(rng).unwrap();
+
+        // as in the puzzle inputs the guard has to leave the map when no obstruction is added
+        let rows = grid.chunks(size).map(|row| row.to_vec()).collect_vec();
+        let dir = match grid[start] { '^' => (0, -1), '>' => (1, 0), 'v' => (0, 1), _ => (-1, 0) };
+
+        if !day_6_is_loop(&rows, ((start % size) as isize, (start / size) as isize), dir) {
+            return grid_to_string(&grid, size);
+        }
+    }
+}
+
+// Calibration equations, about half of which are solvable using +, * and ||.
+pub fn day_7(rng: &mut GeneratorRng, size: usize) -> String {
+    return (0..size).map(|_| {
+        let xs = (0..rng.gen_range(2..=6)).map(|_| rng.gen_range(1..20usize)).collect_vec();
+
+        let y = if rng.gen_bool(0.5) {
+            xs[1..].iter().fold(xs[0], |prev, &x| match rng.gen_range(0..3) {
+                0 => prev + x,
+                1 => prev * x,
+                _ => prev * 10usize.pow(x.ilog10() + 1) + x,
+            })
+        } else {
+            rng.gen_range(1..1000000)
+        };
+
+        format!("{y}: {}", xs.into_iter().join(" "))
+    }).join("\n");
+}
+
+// An antenna map using a handful of frequencies.
+pub fn day_8(rng: &mut GeneratorRng, size: usize) -> String {
+    const FREQUENCIES: &[u8] = b"0123456789aAbBcCdDeEfF";
+
+    let frequencies = &FREQUENCIES[..rng.gen_range(1..=FREQUENCIES.len())];
+    let grid = (0..size * size).map(|_| if rng.gen_bool(0.04) {
+        *frequencies.choose(rng).unwrap() as char
+    } else {
+        '.'
+    }).collect_vec();
+
+    return grid_to_string(&grid, size);
+}
+
+// A disk map of size files with non-empty files and possibly empty gaps.
+pub fn day_9(rng: &mut GeneratorRng, size: usize) -> String {
+    return (0..2 * size.max(1) - 1)
+        .map(|i| if i % 2 == 0 { rng.gen_range(1..=9) } else { rng.gen_range(0..=9) })
+        .join("");
+}
+
+// A topographic map of random heights with some planted hiking trails.
+pub fn day_10(rng: &mut GeneratorRng, size: usize) -> String {
+    let mut grid = (0..size * size).map(|_| rng.gen_range(0..10u8)).collect_vec();
+
+    for _ in 0..size {
+        let mut p = (rng.gen_range(0..size) as isize, rng.gen_range(0..size) as isize);
+
+        for height in 0..10 {
+            grid[p.1 as usize * size + p.0 as usize] = height;
+
+            let d = *[(-1, 0), (1, 0), (0, -1), (0, 1)].choose(rng).unwrap();
+            let next = (p.0 + d.0, p.1 + d.1);
+
+            if next.0 < 0 || next.1 < 0 || next.0 >= size as isize || next.1 >= size as isize {
+                break;
+            }
+
+            p = next;
+        }
+    }
+
+    return grid_to_string(&grid.into_iter().map(|x| (b'0' + x) as char).collect_vec(), size);
+}
+
+pub fn day_11(rng: &mut GeneratorRng, size: usize) -> String {
+    return (0..size).map(|_| rng.gen_range(0..1000000usize)).join(" ");
+}
+
+// A garden where plots tend to copy their left or upper neighbor, forming regions.
+pub fn day_12(rng: &mut GeneratorRng, size: usize) -> String {
+    let mut grid = Vec::with_capacity(size * size);
+
+    for i in 0..size * size {
+        let (x, y) = (i % size, i / size);
+
+        grid.push(match rng.gen_range(0..10) {
+            0..=3 if x > 0 => grid[i - 1],
+            4..=7 if y > 0 => grid[i - size],
+            _ => (b'A' + rng.gen_range(0..6)) as char,
+        });
+    }
+
+    return grid_to_string(&grid, size);
+}
+
+// Claw machines with linearly independent buttons, about half of them with a solution.
+pub fn day_13(rng: &mut GeneratorRng, size: usize) -> String {
+    return (0..size).map(|_| {
+        let (a, b) = loop {
+            let a = (rng.gen_range(10..100usize), rng.gen_range(10..100usize));
+            let b = (rng.gen_range(10..100usize), rng.gen_range(10..100usize));
+
+            if a.0 * b.1 != a.1 * b.0 {
+                break (a, b);
+            }
+        };
+
+        let c = if rng.gen_bool(0.5) {
+            let (n, m) = (rng.gen_range(0..100), rng.gen_range(0..100));
+            (a.0 * n + b.0 * m, a.1 * n + b.1 * m)
+        } else {
+            let mut c = (rng.gen_range(1000..20000usize), rng.gen_range(1000..20000usize));
+
+            // with the determinant dividing both cramer numerators the prize might still be reachable
+            let det = (a.0 * b.1) as isize - (a.1 * b.0) as isize;
+            while ((c.0 * b.1) as isize - (c.1 * b.0) as isize) % det == 0
+                && ((a.0 * c.1) as isize - (a.1 * c.0) as isize) % det == 0 {
+                c.0 += 1;
+            }
+
+            c
+        };
+
+        format!(
+            "Button A: X+{}, Y+{}\nButton B: X+{}, Y+{}\nPrize: X={}, Y={}\n",
+            a.0, a.1, b.0, b.1, c.0, c.1,
+        )
+    }).join("\n");
+}
+
+// Robots on the fixed 101 x 103 floor.
+pub fn day_14(rng: &mut GeneratorRng, size: usize) -> String {
+    return (0..size).map(|_| format!(
+        "p={},{} v={},{}",
+        rng.gen_range(0..101), rng.gen_range(0..103), rng.gen_range(-100..=100), rng.gen_range(-100..=100),
+    )).join("\n");
+}
+
+// A walled warehouse with boxes and a single robot, followed by the robot moves.
+pub fn day_15(rng: &mut GeneratorRng, size: usize) -> String {
+    let size = size.max(4);
+
+    let mut grid = (0..size * size).map(|i| {
+        let (x, y) = (i % size, i / size);
+
+        if x == 0 || y == 0 || x == size - 1 || y == size - 1 {
+            '#'
+        } else {
+            match rng.gen_range(0..10) {
+                0 => '#',
+                1..=3 => 'O',
+                _ => '.',
+            }
+        }
+    }).collect_vec();
+
+    let robot = rng.gen_range(1..size - 1) * size + rng.gen_range(1..size - 1);
+    grid[robot] = '@';
+
+    let moves = (0..size * size * 2).map(|_| *['<', '>', '^', 'v'].choose(rng).unwrap()).collect_vec();
+
+    return format!("{}\n\n{}", grid_to_string(&grid, size), grid_to_string(&moves, 70));
+}
+
+// A maze with loops, with S in the bottom left and E in the top right corner.
+pub fn day_16(rng: &mut GeneratorRng, size: usize) -> String {
+    let size = size.max(1);
+    let width = 2 * size + 1;
+
+    let (walls, _) = carve_maze(rng, size, size);
+    let mut grid = walls.into_iter().map(|wall| if wall { '#' } else { '.' }).collect_vec();
+
+    for _ in 0..size {
+        let (x, y) = (rng.gen_range(1..width - 1), rng.gen_range(1..width - 1));
+        if (x + y) % 2 == 1 {
+            grid[y * width + x] = '.';
+        }
+    }
+
+    grid[(width - 2) * width + 1] = 'S';
+    grid[width + width - 2] = 'E';
+
+    return grid_to_string(&grid, width);
+}
+
+// A program of the shape of the puzzle inputs which is guaranteed to have a quine starting value.
+pub fn day_17(rng: &mut GeneratorRng, _size: usize) -> String {
+    let code = loop {
+        let code = vec![2, 4, 1, rng.gen_range(0..8), 7, 5, 1, rng.gen_range(0..8), 0, 3, 4, 3, 5, 5, 3, 0];
+
+        // every iteration only depends on the remaining bits of A and shifts A by three bits,
+        // hence the quine can be searched for starting with its most significant bits
+        let mut candidates = vec![0];
+        for i in (0..code.len()).rev() {
+            candidates = candidates.into_iter()
+                .flat_map(|a| (0..8).map(move |d| a * 8 + d))
+                .filter(|&a| a != 0 && eval_program(&code, &[a, 0, 0], |_| {}) == code[i..])
+                .collect();
+        }
+
+        if !candidates.is_empty() {
+            break code;
+        }
+    };
+
+    return format!(
+        "Register A: {}\nRegister B: 0\nRegister C: 0\n\nProgram: {}",
+        rng.gen_range(1..1usize << 30), code.into_iter().join(","),
+    );
+}
+
+// Every byte position of the fixed 71 x 71 memory space except start and end, such that the first
+// 1024 bytes keep the end reachable.
+pub fn day_18(rng: &mut GeneratorRng, _size: usize) -> String {
+    const W: usize = 71;
+    const H: usize = 71;
+
+    let mut bytes = (0..W * H)
+        .map(|i| (i % W, i / W))
+        .filter(|&p| p != (0, 0) && p != (W - 1, H - 1))
+        .collect_vec();
+
+    loop {
+        bytes.shuffle(rng);
+
+        let blocked: HashSet<_> = bytes[..1024].iter().cloned().collect();
+        let mut found = HashSet::from([(0, 0)]);
+        let mut queue: Vec<(usize, usize)> = vec![(0, 0)];
+
+        while let Some((x, y)) = queue.pop() {
+            for (nx, ny) in [(x + 1, y), (x, y + 1), (x.wrapping_sub(1), y), (x, y.wrapping_sub(1))] {
+                if nx < W && ny < H && !blocked.contains(&(nx, ny)) && found.insert((nx, ny)) {
+                    queue.push((nx, ny));
+                }
+            }
+        }
+
+        if found.contains(&(W - 1, H - 1)) {
+            break;
+        }
+    }
+
+    return bytes.into_iter().map(|(x, y)| format!("{x},{y}")).join("\n");
+}
+
+// Towel patterns followed by designs, about half of which are concatenations of patterns.
+pub fn day_19(rng: &mut GeneratorRng, size: usize) -> String {
+    const COLORS: &[u8] = b"wubrg";
+
+    let mut patterns = BTreeSet::new();
+    while patterns.len() < size.max(1) * 4 {
+        patterns.insert((0..rng.gen_range(1..=6)).map(|_| *COLORS.choose(rng).unwrap() as char).collect::<String>());
+    }
+
+    let mut patterns = patterns.into_iter().collect_vec();
+    patterns.shuffle(rng);
+
+    let designs = (0..size).map(|_| if rng.gen_bool(0.5) {
+        (0..rng.gen_range(3..12)).map(|_| patterns.choose(rng).unwrap().as_str()).collect::<String>()
+    } else {
+        (0..rng.gen_range(10..40)).map(|_| *COLORS.choose(rng).unwrap() as char).collect::<String>()
+    });
+
+    return format!("{}\n\n{}", patterns.iter().join(", "), designs.into_iter().join("\n"));
+}
+
+// A single race track through a maze from S to E surrounded by walls.
+pub fn day_20(rng: &mut GeneratorRng, size: usize) -> String {
+    let size = size.max(1);
+    let width = 2 * size + 1;
+
+    let (_, path) = carve_maze(rng, size, size);
+    let mut grid = vec!['#'; width * width];
+
+    for &(x, y) in path.iter() {
+        grid[y * width + x] = '.';
+    }
+
+    let (sx, sy) = path[0];
+    let (ex, ey) = path[path.len() - 1];
+    grid[sy * width + sx] = 'S';
+    grid[ey * width + ex] = 'E';
+
+    return grid_to_string(&grid, width);
+}
+
+// Door codes of three distinct digits followed by A.
+pub fn day_21(rng: &mut GeneratorRng, size: usize) -> String {
+    return (0..size).map(|_| format!("{}A", b"0123456789".choose_multiple(rng, 3).map(|&x| x as char).collect::<String>())).join("\n");
+}
+
+pub fn day_22(rng: &mut GeneratorRng, size: usize) -> String {
+    return (0..size).map(|_| rng.gen_range(1..16777216usize)).join("\n");
+}
+
+// A sparse network of computers with a planted unique maximum clique.
+pub fn day_23(rng: &mut GeneratorRng, size: usize) -> String {
+    const CLIQUE: usize = 6;
+
+    let mut used = HashSet::new();
+    let names = (0..size.max(2 * CLIQUE))
+        .map(|_| random_name(rng, b"abcdefghijklmnopqrstuvwxyz", 2, &mut used))
+        .collect_vec();
+
+    let mut edges = BTreeSet::new();
+    for i in 0..CLIQUE {
+        for j in i + 1..CLIQUE {
+            edges.insert((i, j));
+        }
+    }
+
+    for i in 0..names.len() {
+        for _ in 0..2 {
+            let j = rng.gen_range(0..names.len());
+            if i != j && (i >= CLIQUE || j >= CLIQUE) {
+                edges.insert((i.min(j), i.max(j)));
+            }
+        }
+    }
+
+    let mut lines = edges.into_iter()
+        .map(|(i, j)| if rng.gen_bool(0.5) { format!("{}-{}", names[i], names[j]) } else { format!("{}-{}", names[j], names[i]) })
+        .collect_vec();
+    lines.shuffle(rng);
+
+    return lines.join("\n");
+}
+
+// Whether the gates can be evaluated in some order, i.e. whether there is no cyclic dependency.
+fn is_acyclic(gates: &[(String, &str, String, String)]) -> bool {
+    let is_available = |available: &HashSet<&str>, wire: &str| {
+        wire.starts_with('x') || wire.starts_with('y') || available.contains(wire)
+    };
+
+    let mut available = HashSet::new();
+    let mut remaining = gates.iter().collect_vec();
+
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining.into_iter()
+            .partition(|g| is_available(&available, &g.0) && is_available(&available, &g.2));
+
+        if ready.is_empty() {
+            return false;
+        }
+
+        available.extend(ready.into_iter().map(|g| g.3.as_str()));
+        remaining = blocked;
+    }
+
+    return true;
+}
+
+// A ripple-carry adder for size bit inputs with four pairs of swapped gate outputs.
+pub fn day_24(rng: &mut GeneratorRng, size: usize) -> String {
+    let bits = size.max(2);
+
+    let mut used = HashSet::new();
+    let mut wire = |rng: &mut GeneratorRng| random_name(rng, b"abcdefghijklmnopqrstuvw", 3, &mut used);
+
+    let mut gates = Vec::new();
+    let mut carry = wire(rng);
+    gates.push((String::from("x00"), "XOR", String::from("y00"), String::from("z00")));
+    gates.push((String::from("x00"), "AND", String::from("y00"), carry.clone()));
+
+    for i in 1..bits {
+        let (x, y, z) = (format!("x{i:02}"), format!("y{i:02}"), format!("z{i:02}"));
+        let (sum, and, carry_and) = (wire(rng), wire(rng), wire(rng));
+        let next_carry = if i == bits - 1 { format!("z{bits:02}") } else { wire(rng) };
+
+        gates.push((x.clone(), "XOR", y.clone(), sum.clone()));
+        gates.push((sum.clone(), "XOR", carry.clone(), z));
+        gates.push((x, "AND", y, and.clone()));
+        gates.push((sum, "AND", carry, carry_and.clone()));
+        gates.push((and, "OR", carry_and, next_carry.clone()));
+
+        carry = next_carry;
+    }
+
+    let mut swapped = HashSet::new();
+    let mut swaps = 0;
+
+    while swaps < 4.min(gates.len() / 2) {
+        let (i, j) = (rng.gen_range(0..gates.len()), rng.gen_range(0..gates.len()));
+
+        if i == j || swapped.contains(&i) || swapped.contains(&j) { continue; }
+
+        let tmp = gates[i].3.clone();
+        gates[i].3 = gates[j].3.clone();
+        gates[j].3 = tmp;
+
+        if is_acyclic(&gates) {
+            swapped.insert(i);
+            swapped.insert(j);
+            swaps += 1;
+        } else {
+            let tmp = gates[i].3.clone();
+            gates[i].3 = gates[j].3.clone();
+            gates[j].3 = tmp;
+        }
+    }
+
+    gates.shuffle(rng);
+
+    let inputs = ["x", "y"].into_iter()
+        .flat_map(|name| (0..bits).map(move |i| format!("{name}{i:02}")))
+        .map(|name| format!("{name}: {}", rng.gen_range(0..2)))
+        .join("\n");
+
+    let gates = gates.into_iter()
+        .map(|(left, op, right, output)| if rng.gen_bool(0.5) {
+            format!("{left} {op} {right} -> {output}")
+        } else {
+            format!("{right} {op} {left} -> {output}")
+        })
+        .join("\n");
+
+    return format!("{inputs}\n\n{gates}");
+}
+
+// Lock and key schematics with random pin heights.
+pub fn day_25(rng: &mut GeneratorRng, size: usize) -> String {
+    return (0..size).map(|_| {
+        let lock = rng.gen_bool(0.5);
+        let heights = (0..5).map(|_| rng.gen_range(0..=5)).collect_vec();
+
+        (0..7).map(|row| (0..5).map(|i| {
+            let filled = if lock { row <= heights[i] } else { 6 - row <= heights[i] };
+            if filled { '#' } else { '.' }
+        }).collect::<String>()).join("\n")
+    }).join("\n\n");
+}
@@ -0,0 +1,10 @@
+use seq_macro::seq;
+
+pub mod common;
+pub mod differential;
+pub mod generator;
+pub mod reference;
+pub mod stats;
+seq!(N in 1..=25 {
+   pub mod day_~N;
+});
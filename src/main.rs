@@ -1,23 +1,231 @@
 use seq_macro::seq;
 
-mod common;
-seq!(N in 1..=25 {
-   mod day_~N;
-});
-
+use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::time::Instant;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use log::{LevelFilter, Log, Metadata, Record};
+use advent_of_code_2024::{day_1, day_2, day_3, day_4, day_5, day_6, day_7, day_8, day_9, day_10, differential, stats};
 
-const fn log_day(day: usize) -> bool {
-    return day == 24;
+// Logs to stderr, with per day targets such as "day15" matched exactly rather than by prefix,
+// such that "day1" does not enable "day15".
+struct CliLogger {
+    default: LevelFilter,
+    targets: HashMap<String, LevelFilter>,
 }
 
-fn main() {
+impl CliLogger {
+    fn level(&self, target: &str) -> LevelFilter {
+        return self.targets.get(target).cloned().unwrap_or(self.default);
+    }
+}
+
+impl Log for CliLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        return metadata.level() <= self.level(metadata.target());
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn init_logging(verbosity: usize, filters: &[String]) {
+    let mut logger = CliLogger {
+        default: match verbosity {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        },
+        targets: HashMap::new(),
+    };
+
+    for filter in filters {
+        let (target, level) = filter.split_once('=').unwrap_or_else(|| panic!("Invalid log filter {filter}"));
+        logger.targets.insert(target.to_string(), level.parse().unwrap_or_else(|_| panic!("Invalid log level {level}")));
+    }
+
+    log::set_max_level(logger.targets.values().cloned().fold(logger.default, |a, b| a.max(b)));
+    log::set_boxed_logger(Box::new(logger)).unwrap();
+}
+
+fn run_differential() {
+    for check in differential::checks() {
+        match check.run(0..100, Duration::from_secs(10)) {
+            Some(mismatch) => println!(
+                "Day {} Part {}: Mismatch for seed {}; Fast = {}; Reference = {};\n{}",
+                mismatch.day, mismatch.part, mismatch.seed, mismatch.fast, mismatch.reference, mismatch.input
+            ),
+            None => println!("Day {} Part {}: No mismatch;", check.day, check.part),
+        }
+    }
+}
+
+fn data_file(day: usize) -> fs::File {
+    return fs::File::open(format!("./data/day_{day}.txt")).unwrap();
+}
+
+fn parse_uneven_policy(arg: Option<&String>) -> day_1::UnevenPolicy {
+    return match arg.map(|x| x.as_str()) {
+        None | Some("error") => day_1::UnevenPolicy::Error,
+        Some("truncate") => day_1::UnevenPolicy::Truncate,
+        Some(x) => day_1::UnevenPolicy::Pad(
+            x.strip_prefix("pad=").and_then(|x| x.parse().ok()).unwrap_or_else(|| panic!("Invalid policy {x}"))
+        ),
+    };
+}
+
+// Prints the report of a day, where the meaning of the remaining arguments depends on the day.
+fn run_report(args: &[String]) {
+    let day: usize = args.first().and_then(|x| x.parse().ok()).expect("Missing report day");
+    let mut out = io::stdout().lock();
+
+    match day {
+        1 if args.get(1).map(|x| x.as_str()) == Some("columns") => {
+            match day_1::compare_columns(data_file(day), parse_uneven_policy(args.get(2))) {
+                Ok(comparison) => {
+                    writeln!(out, "lengths {:?}", comparison.lengths).unwrap();
+                    comparison.distance.iter().for_each(|row| writeln!(out, "distance {row:?}").unwrap());
+                    comparison.similarity.iter().for_each(|row| writeln!(out, "similarity {row:?}").unwrap());
+                },
+                Err(err) => eprintln!("Error: {err}"),
+            }
+        },
+        1 => {
+            let top_k = args.get(1).map_or(10, |x| x.parse().expect("Invalid top k"));
+
+            if let Err(err) = day_1::write_report(data_file(day), top_k, parse_uneven_policy(args.get(2)), &mut out) {
+                eprintln!("Error: {err}");
+            }
+        },
+        2 => {
+            let max_removals = args.get(1).map_or(1, |x| x.parse().expect("Invalid removal count"));
+            let min_step = args.get(2).map_or(1, |x| x.parse().expect("Invalid minimal step"));
+            let max_step = args.get(3).map_or(3, |x| x.parse().expect("Invalid maximal step"));
+            day_2::write_report(data_file(day), max_removals, min_step..=max_step, &mut out).unwrap();
+        },
+        3 => {
+            let max_digits = args.get(1).map_or(day_3::MAX_DIGITS, |x| {
+                x.parse().ok().filter(|&d| d <= day_3::MAX_DIGIT_LIMIT).unwrap_or_else(|| panic!("Invalid digit limit {x}"))
+            });
+            let near_misses = args.get(2).map(|x| x.as_str()) == Some("near-misses");
+            day_3::write_report(data_file(day), max_digits, near_misses, &mut out).unwrap();
+        },
+        4 if args.get(1).map(|x| x.as_str()) == Some("template") => {
+            let template = day_4::Template::parse(args.get(2).map_or("M.S/.A./M.S", |x| x.as_str()));
+            day_4::write_template_report(data_file(day), &template, &mut out).unwrap();
+        },
+        4 => {
+            let options = day_4::SearchOptions {
+                wrap: args.iter().any(|x| x == "--wrap"),
+                overlaps: !args.iter().any(|x| x == "--no-overlaps"),
+            };
+
+            let mut words = args[1..].iter().filter(|x| !x.starts_with("--")).map(|x| x.as_str()).collect::<Vec<_>>();
+            if words.is_empty() { words.push("XMAS"); }
+
+            day_4::write_report(data_file(day), &words, options, &mut out).unwrap();
+        },
+        5 => day_5::write_report(data_file(day), &mut out).unwrap(),
+        6 if args.get(1).map(|x| x.as_str()) == Some("patrol") => {
+            let turn = match args.get(2).map(|x| x.as_str()) {
+                None | Some("cw") => day_6::TurnRule::Clockwise,
+                Some("ccw") => day_6::TurnRule::CounterClockwise,
+                Some("alternating") => day_6::TurnRule::Alternating,
+                Some("stop") => day_6::TurnRule::Stop,
+                Some(x) => panic!("Invalid turn rule {x}"),
+            };
+
+            let collisions = match args.get(3).map(|x| x.as_str()) {
+                None | Some("ignore") => day_6::CollisionPolicy::Ignore,
+                Some("block") => day_6::CollisionPolicy::Block,
+                Some("halt") => day_6::CollisionPolicy::Halt,
+                Some(x) => panic!("Invalid collision policy {x}"),
+            };
+
+            day_6::write_patrol_report(data_file(day), turn, collisions, &mut out).unwrap();
+        },
+        7 => {
+            let all_witnesses = args.iter().any(|x| x == "all");
+            let mut operators = args[1..].iter()
+                .filter(|x| *x != "all")
+                .map(|x| day_7::parse_operator(x).unwrap_or_else(|| panic!("Invalid operator {x}")))
+                .collect::<Vec<_>>();
+
+            if operators.is_empty() {
+                operators = ["+", "*", "||"].into_iter().map(|x| day_7::parse_operator(x).unwrap()).collect();
+            }
+
+            let operators = operators.iter().map(|x| x.as_ref()).collect::<Vec<_>>();
+            day_7::write_report(data_file(day), &operators, all_witnesses, &mut out).unwrap();
+        },
+        6 => {
+            let render = args.get(1).map(|x| (
+                x.parse().expect("Invalid obstruction x"),
+                args.get(2).and_then(|y| y.parse().ok()).expect("Invalid obstruction y"),
+            ));
+            day_6::write_report(data_file(day), render, &mut out).unwrap();
+        },
+        8 => {
+            let render = args.iter().any(|x| x == "map");
+            let interior = args.iter().any(|x| x == "interior");
+            let harmonics = if args.iter().any(|x| x == "line") {
+                day_8::Harmonics::Line
+            } else {
+                let mut ratios = args[1..].iter()
+                    .filter(|x| *x != "map" && *x != "interior")
+                    .map(|x| x.parse().ok().filter(|&r| r > 0).unwrap_or_else(|| panic!("Invalid ratio {x}")))
+                    .collect::<Vec<_>>();
+
+                if ratios.is_empty() { ratios.push(2); }
+                day_8::Harmonics::Ratios { ratios, interior }
+            };
+
+            day_8::write_report(data_file(day), &harmonics, render, &mut out).unwrap();
+        },
+        9 => {
+            let layout = args.iter().any(|x| x == "layout");
+            let mut strategies = args[1..].iter()
+                .filter(|x| *x != "layout")
+                .map(|x| day_9::Strategy::parse(x).unwrap_or_else(|| panic!("Invalid strategy {x}")))
+                .collect::<Vec<_>>();
+
+            if strategies.is_empty() { strategies.extend(day_9::Strategy::ALL); }
+            day_9::write_report(data_file(day), &strategies, layout, &mut out).unwrap();
+        },
+        10 => {
+            let parse_range = |x: &str| x.split_once('-')
+                .and_then(|(a, b)| Some(a.parse().ok()?..=b.parse().ok()?))
+                .unwrap_or_else(|| panic!("Invalid range {x}"));
+
+            let mut rules = day_10::TrailRules::default();
+            for x in args[1..].iter().filter(|x| *x != "trails") {
+                if let Some(heights) = x.strip_prefix("heights=") {
+                    rules.heights = parse_range(heights);
+                } else if let Some(steps) = x.strip_prefix("steps=") {
+                    rules.steps = parse_range(steps);
+                } else {
+                    panic!("Invalid argument {x}");
+                }
+            }
+
+            day_10::write_report(data_file(day), rules, args.iter().any(|x| x == "trails"), &mut out).unwrap();
+        },
+        _ => panic!("No report for day {day}"),
+    }
+}
+
+fn run_day(day: usize) {
     seq!(N in 1..=25 {
-        if log_day(N) {
+        if day == N {
             let now = Instant::now();
-            let sol_1 = crate::day_~N::part_1(fs::File::open(concat!("./data/day_", stringify!(N), ".txt")).unwrap());
-            let sol_2 = crate::day_~N::part_2(fs::File::open(concat!("./data/day_", stringify!(N), ".txt")).unwrap());
+            let sol_1 = advent_of_code_2024::day_~N::part_1(fs::File::open(concat!("./data/day_", stringify!(N), ".txt")).unwrap());
+            let sol_2 = advent_of_code_2024::day_~N::part_2(fs::File::open(concat!("./data/day_", stringify!(N), ".txt")).unwrap());
             println!(
                 concat!("Day ", stringify!(N), ": Part 1 = {}; Part 2 = {}; Took {:.2}ms;"),
                 sol_1, sol_2, now.elapsed().as_secs_f32() * 1000.0f32
@@ -25,3 +233,70 @@ fn main() {
         }
     });
 }
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum StatsOutput { None, Text, Json }
+
+// Usage: advent_of_code_2024 [-v | -vv] [--log TARGET=LEVEL]... [--stats | --stats=json]
+//     [differential | report DAY [ARGS...] | DAY...],
+// runs all days if none are given.
+fn main() {
+    let mut verbosity = 0;
+    let mut filters = Vec::new();
+    let mut stats_output = StatsOutput::None;
+    let mut args = Vec::new();
+
+    let mut raw_args = env::args().skip(1);
+    while let Some(arg) = raw_args.next() {
+        match arg.as_str() {
+            "-v" => { verbosity += 1; },
+            "-vv" => { verbosity += 2; },
+            "--log" => { filters.push(raw_args.next().expect("Missing log filter")); },
+            "--stats" => { stats_output = StatsOutput::Text; },
+            "--stats=json" => { stats_output = StatsOutput::Json; },
+            _ => { args.push(arg); },
+        }
+    }
+
+    init_logging(verbosity, &filters);
+
+    if stats_output != StatsOutput::None {
+        stats::enable();
+    }
+
+    if args.first().map(|x| x.as_str()) == Some("differential") {
+        run_differential();
+        return;
+    }
+
+    if args.first().map(|x| x.as_str()) == Some("report") {
+        run_report(&args[1..]);
+        return;
+    }
+
+    let days: Vec<usize> = if args.is_empty() {
+        (1..=25).collect()
+    } else {
+        args.iter().map(|x| x.parse().unwrap_or_else(|_| panic!("Invalid day {x}"))).collect()
+    };
+
+    let mut all_stats = stats::Stats::default();
+
+    for day in days {
+        if !(1..=25).contains(&day) {
+            panic!("Invalid day {day}");
+        }
+
+        run_day(day);
+
+        let day_stats = stats::take_stats();
+        if stats_output == StatsOutput::Text && !day_stats.is_empty() {
+            print!("{day_stats}");
+        }
+        all_stats.targets.extend(day_stats.targets);
+    }
+
+    if stats_output == StatsOutput::Json {
+        println!("{}", all_stats.to_json());
+    }
+}
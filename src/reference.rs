@@ -0,0 +1,308 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use itertools::Itertools;
+use crate::common::Matrix;
+use crate::day_17::{eval_program, parse_program};
+
+// Slow but obviously correct implementations of the days whose fast solvers rely on shortcuts,
+// used by the differential testing in the differential module.
+
+// Whether the guard walking on the grid ends up in a loop, by tracking every visited position and direction.
+pub(crate) fn day_6_is_loop(grid: &[Vec<char>], mut pos: (isize, isize), mut dir: (isize, isize)) -> bool {
+    let mut seen = HashSet::new();
+
+    while seen.insert((pos, dir)) {
+        let next = (pos.0 + dir.0, pos.1 + dir.1);
+
+        match grid.get(next.1 as usize).and_then(|row| row.get(next.0 as usize)) {
+            None => return false,
+            Some('#') => dir = (-dir.1, dir.0),
+            Some(_) => pos = next,
+        }
+    }
+
+    return true;
+}
+
+pub fn day_6_part_2(data: File) -> usize {
+    let mut grid = BufReader::new(data).lines().flatten().map(|line| line.chars().collect_vec()).collect_vec();
+
+    let start = grid.iter().enumerate()
+        .find_map(|(y, row)| row.iter().position(|x| "^>v<".contains(*x)).map(|x| (x, y)));
+    let Some((x, y)) = start else { return 0; };
+
+    let dir = match grid[y][x] { '^' => (0, -1), '>' => (1, 0), 'v' => (0, 1), _ => (-1, 0) };
+    let start = (x as isize, y as isize);
+    let mut count = 0;
+
+    // tries an obstruction on every free cell other than the start
+    for oy in 0..grid.len() {
+        for ox in 0..grid[oy].len() {
+            if grid[oy][ox] != '.' {
+                continue;
+            }
+
+            grid[oy][ox] = '#';
+            if day_6_is_loop(&grid, start, dir) {
+                count += 1;
+            }
+            grid[oy][ox] = '.';
+        }
+    }
+
+    return count;
+}
+
+fn day_7_solvable(y: usize, xs: &[usize], concat: bool) -> bool {
+    let op_count = if concat { 3 } else { 2 };
+
+    // tries every assignment of operators, evaluated left to right
+    return (0..xs.len() - 1)
+        .map(|_| 0..op_count)
+        .multi_cartesian_product()
+        .any(|ops| {
+            let mut value = xs[0] as u128;
+
+            for (op, &x) in ops.into_iter().zip(xs[1..].iter()) {
+                value = match op {
+                    0 => value + x as u128,
+                    1 => value * x as u128,
+                    _ => format!("{value}{x}").parse::<u128>().unwrap(),
+                };
+            }
+
+            return value == y as u128;
+        });
+}
+
+fn day_7(data: File, concat: bool) -> usize {
+    let mut total = 0;
+
+    for line in BufReader::new(data).lines().flatten() {
+        let (y, xs) = line.split_once(':').unwrap();
+        let y = y.parse::<usize>().unwrap();
+        let xs = xs.split_whitespace().map(|x| x.parse::<usize>().unwrap()).collect_vec();
+
+        if day_7_solvable(y, &xs, concat) {
+            total += y;
+        }
+    }
+
+    return total;
+}
+
+pub fn day_7_part_1(data: File) -> usize {
+    return day_7(data, false);
+}
+
+pub fn day_7_part_2(data: File) -> usize {
+    return day_7(data, true);
+}
+
+fn day_9_disk(data: File) -> Vec<Option<usize>> {
+    let line = BufReader::new(data).lines().flatten().next().unwrap();
+    let mut disk = Vec::new();
+
+    for (i, c) in line.chars().enumerate() {
+        for _ in 0..c.to_digit(10).unwrap() {
+            disk.push(if i % 2 == 0 { Some(i / 2) } else { None });
+        }
+    }
+
+    return disk;
+}
+
+fn day_9_checksum(disk: &[Option<usize>]) -> usize {
+    return disk.iter().enumerate().map(|(i, x)| i * x.unwrap_or(0)).sum();
+}
+
+pub fn day_9_part_1(data: File) -> usize {
+    let mut disk = day_9_disk(data);
+
+    // moves the last file block into the first free block until they are in order
+    loop {
+        let first_free = disk.iter().position(|x| x.is_none());
+        let last_file = disk.iter().rposition(|x| x.is_some());
+
+        match (first_free, last_file) {
+            (Some(i), Some(j)) if i < j => disk.swap(i, j),
+            _ => break,
+        }
+    }
+
+    return day_9_checksum(&disk);
+}
+
+pub fn day_9_part_2(data: File) -> usize {
+    let mut disk = day_9_disk(data);
+    let file_count = disk.iter().flatten().max().map_or(0, |&id| id + 1);
+
+    for id in (0..file_count).rev() {
+        let start = disk.iter().position(|&x| x == Some(id)).unwrap();
+        let len = disk.iter().filter(|&&x| x == Some(id)).count();
+
+        // moves the whole file to the leftmost span of free blocks that fits it
+        if let Some(target) = (0..start).find(|&i| i + len <= start && disk[i..i + len].iter().all(|x| x.is_none())) {
+            for k in 0..len {
+                disk.swap(target + k, start + k);
+            }
+        }
+    }
+
+    return day_9_checksum(&disk);
+}
+
+fn day_12_regions(matrix: &Matrix<char>) -> Vec<HashSet<(isize, isize)>> {
+    let mut assigned = HashSet::new();
+    let mut regions = Vec::new();
+
+    for i in 0..matrix.buffer.len() {
+        let p = matrix.index_to_point(i).unwrap();
+
+        if assigned.insert(p) {
+            let mut region = HashSet::from([p]);
+            let mut queue = vec![p];
+
+            while let Some(q) = queue.pop() {
+                for (r, &x) in matrix.neighborhood_four_way(q) {
+                    if x == matrix.buffer[i] && assigned.insert(r) {
+                        region.insert(r);
+                        queue.push(r);
+                    }
+                }
+            }
+
+            regions.push(region);
+        }
+    }
+
+    return regions;
+}
+
+// The boundary of a region as the set of (cell, outward direction) pairs.
+fn day_12_boundary(region: &HashSet<(isize, isize)>) -> HashSet<((isize, isize), (isize, isize))> {
+    return region.iter()
+        .flat_map(|&p| [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter().map(move |d| (p, d)))
+        .filter(|&(p, d)| !region.contains(&(p.0 + d.0, p.1 + d.1)))
+        .collect();
+}
+
+pub fn day_12_part_1(data: File) -> usize {
+    let matrix = Matrix::from_lines(BufReader::new(data).lines().flatten(), |x, _| x);
+
+    return day_12_regions(&matrix).iter()
+        .map(|region| region.len() * day_12_boundary(region).len())
+        .sum();
+}
+
+pub fn day_12_part_2(data: File) -> usize {
+    let matrix = Matrix::from_lines(BufReader::new(data).lines().flatten(), |x, _| x);
+
+    return day_12_regions(&matrix).iter()
+        .map(|region| {
+            let boundary = day_12_boundary(region);
+
+            // every side is counted once at its boundary edge whose predecessor along the side,
+            // i.e. the edge of the same direction on the neighboring cell, is not part of the boundary
+            let sides = boundary.iter()
+                .filter(|&&(p, d)| !boundary.contains(&((p.0 + d.1, p.1 + d.0), d)))
+                .count();
+
+            region.len() * sides
+        })
+        .sum();
+}
+
+pub fn day_17_part_2(data: File) -> usize {
+    let (code, _) = parse_program(data);
+
+    // the search is exhaustive for programs that form a single loop which shifts A by three bits
+    // once per iteration, since the outputs of a suffix of iterations only depend on the high bits of A
+    let instructions = code.chunks(2).collect_vec();
+    assert_eq!(instructions.last(), Some(&&[3, 0][..]));
+    assert_eq!(instructions.iter().filter(|x| x[0] == 3).count(), 1);
+    assert_eq!(instructions.iter().filter(|x| x[0] == 0).collect_vec(), vec![&[0, 3]]);
+
+    let mut candidates = vec![0];
+    for i in (0..code.len()).rev() {
+        candidates = candidates.into_iter()
+            .flat_map(|a| (0..8).map(move |d| a * 8 + d))
+            .filter(|&a| eval_program(&code, &[a, 0, 0], |_| {}) == code[i..])
+            .collect();
+    }
+
+    return candidates.into_iter().filter(|&a| a != 0).min().unwrap();
+}
+
+// The minimal number of presses on the outermost directional keypad to type the code when it is
+// relayed through the given number of robot operated directional keypads, found by a breadth-first
+// search over the positions of all robot arms.
+pub fn day_21_cost(code: &str, robots: usize) -> usize {
+    const DIR_PAD: [&str; 2] = ["#^A", "<v>"];
+    const NUM_PAD: [&str; 4] = ["789", "456", "123", "#0A"];
+
+    let find = |pad: &[&str], x: char| pad.iter().enumerate()
+        .find_map(|(y, row)| row.chars().position(|c| c == x).map(|x| (x as isize, y as isize)))
+        .unwrap();
+
+    let key = |pad: &[&str], (x, y): (isize, isize)| {
+        if x < 0 || y < 0 || y as usize >= pad.len() || x as usize >= pad[0].len() { return '#'; }
+        return pad[y as usize].chars().nth(x as usize).unwrap();
+    };
+
+    let code = code.chars().collect_vec();
+
+    // the arms of the robots are ordered from the outermost keypad to the numeric keypad
+    let mut start = vec![find(&DIR_PAD, 'A'); robots];
+    start.push(find(&NUM_PAD, 'A'));
+
+    let mut found = HashMap::from([((start.clone(), 0), 0)]);
+    let mut queue = VecDeque::from([(start, 0)]);
+
+    while let Some((arms, typed)) = queue.pop_front() {
+        let presses = found[&(arms.clone(), typed)];
+
+        if typed == code.len() {
+            return presses;
+        }
+
+        for pressed in "<>^vA".chars() {
+            let mut arms = arms.clone();
+            let mut typed = typed;
+            let mut pressed = pressed;
+            let mut valid = true;
+
+            for i in 0..arms.len() {
+                let pad = if i + 1 == arms.len() { &NUM_PAD[..] } else { &DIR_PAD[..] };
+
+                if pressed == 'A' {
+                    pressed = key(pad, arms[i]);
+
+                    if i + 1 == arms.len() {
+                        valid = code[typed] == pressed;
+                        typed += 1;
+                    }
+                } else {
+                    let d = match pressed { '<' => (-1, 0), '>' => (1, 0), '^' => (0, -1), _ => (0, 1) };
+                    arms[i] = (arms[i].0 + d.0, arms[i].1 + d.1);
+                    valid = key(pad, arms[i]) != '#';
+                    break;
+                }
+            }
+
+            if valid && !found.contains_key(&(arms.clone(), typed)) {
+                found.insert((arms.clone(), typed), presses + 1);
+                queue.push_back((arms, typed));
+            }
+        }
+    }
+
+    unreachable!();
+}
+
+pub fn day_21_part_1(data: File) -> usize {
+    return BufReader::new(data).lines().flatten()
+        .map(|line| line[..line.len() - 1].parse::<usize>().unwrap() * day_21_cost(&line, 2))
+        .sum();
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::mem::take;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+
+// Named counters and histograms that solvers can report, grouped by target (e.g. "day16").
+// Collection is disabled by default, in which case recording only costs an atomic load.
+
+#[derive(Debug, Clone, Default)]
+pub struct TargetStats {
+    pub counters: BTreeMap<String, usize>,
+    pub histograms: BTreeMap<String, BTreeMap<usize, usize>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub targets: BTreeMap<String, TargetStats>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref STATS: Mutex<Stats> = Mutex::new(Stats::default());
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    return ENABLED.load(Ordering::Relaxed);
+}
+
+fn with_target(target: &str, f: impl FnOnce(&mut TargetStats)) {
+    let mut stats = STATS.lock().unwrap();
+
+    if let Some(target_stats) = stats.targets.get_mut(target) {
+        f(target_stats);
+    } else {
+        let mut target_stats = TargetStats::default();
+        f(&mut target_stats);
+        stats.targets.insert(target.to_string(), target_stats);
+    }
+}
+
+pub fn count(target: &str, name: &str, amount: usize) {
+    if enabled() {
+        with_target(target, |stats| {
+            *stats.counters.entry(name.to_string()).or_insert(0) += amount;
+        });
+    }
+}
+
+pub fn record(target: &str, name: &str, value: usize) {
+    record_n(target, name, value, 1);
+}
+
+pub fn record_n(target: &str, name: &str, value: usize, occurrences: usize) {
+    if enabled() {
+        with_target(target, |stats| {
+            *stats.histograms.entry(name.to_string()).or_default().entry(value).or_insert(0) += occurrences;
+        });
+    }
+}
+
+// Returns everything recorded so far and resets the statistics.
+pub fn take_stats() -> Stats {
+    return take(&mut *STATS.lock().unwrap());
+}
+
+fn json_string(x: &str) -> String {
+    return format!("\"{}\"", x.replace('\\', "\\\\").replace('"', "\\\""));
+}
+
+impl TargetStats {
+    pub fn to_json(&self) -> String {
+        let counters = self.counters.iter()
+            .map(|(name, value)| format!("{}:{value}", json_string(name)))
+            .join(",");
+
+        let histograms = self.histograms.iter()
+            .map(|(name, histogram)| format!(
+                "{}:{{{}}}",
+                json_string(name),
+                histogram.iter().map(|(value, count)| format!("\"{value}\":{count}")).join(","),
+            ))
+            .join(",");
+
+        return format!("{{\"counters\":{{{counters}}},\"histograms\":{{{histograms}}}}}");
+    }
+}
+
+impl Stats {
+    pub fn is_empty(&self) -> bool {
+        return self.targets.is_empty();
+    }
+
+    pub fn to_json(&self) -> String {
+        return format!(
+            "{{{}}}",
+            self.targets.iter().map(|(target, stats)| format!("{}:{}", json_string(target), stats.to_json())).join(","),
+        );
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (target, stats) in self.targets.iter() {
+            for (name, value) in stats.counters.iter() {
+                writeln!(f, "  {target} {name} = {value}")?;
+            }
+
+            for (name, histogram) in stats.histograms.iter() {
+                writeln!(
+                    f, "  {target} {name} = [{}]",
+                    histogram.iter().map(|(value, count)| format!("{value}: {count}")).join(", "),
+                )?;
+            }
+        }
+
+        return Ok(());
+    }
+}